@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use heimdall::{log, prelude::*};
+use std::time::Duration;
 
 fn main() {
     if let Err(e) = try_main() {
@@ -9,10 +10,12 @@ fn main() {
 }
 
 fn try_main() -> Result<()> {
-    Logger::builder()
+    let guard = Logger::builder()
         .with_address_port("127.0.0.1", 62000)
         .with_app_name("many")
         .with_version("1.0.0")
+        .with_batching(500, Duration::from_millis(50))
+        .with_overflow(Overflow::Block)
         .build()
         .context("Failed to build logger")?;
 
@@ -20,6 +23,7 @@ fn try_main() -> Result<()> {
         log!("Log", "id" => i);
     }
 
-    println!("Log messages sent successfully.");
+    let stats = guard.with_timeout(Duration::from_secs(30)).finish();
+    println!("Logs: {stats}");
     Ok(())
 }
@@ -0,0 +1,27 @@
+use anyhow::{Context, Result};
+use heimdall::{debug, error, info, prelude::*, trace, warn};
+
+fn main() {
+    if let Err(e) = try_main() {
+        eprintln!("Error: {e:?}");
+        std::process::exit(1);
+    }
+}
+
+fn try_main() -> Result<()> {
+    let _guard = Logger::builder()
+        .with_address_port("127.0.0.1", 62000)
+        .with_app_name("levels")
+        .with_version("1.0.0")
+        .build()
+        .context("Failed to build logger")?;
+
+    trace!("Entering main");
+    debug!("Loaded configuration", "entries" => 3);
+    info!("Service started");
+    warn!("Disk usage is high", "used" => "91%");
+    error!("Failed to reach upstream", "host" => "example.com", "attempt" => 2);
+
+    println!("Log messages sent successfully.");
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use heimdall::{info, prelude::*, span, warn};
+use std::time::Duration;
+
+fn main() {
+    if let Err(e) = try_main() {
+        eprintln!("Error: {e:?}");
+        std::process::exit(1);
+    }
+}
+
+fn try_main() -> Result<()> {
+    let _guard = Logger::builder()
+        .with_address_port("127.0.0.1", 62000)
+        .with_app_name("host")
+        .with_version("1.0.0")
+        .build()
+        .context("Failed to build logger")?;
+
+    // Each plugin logs with its own name and version, next to the host's global logger
+    let plugins = ["resize", "thumbnail"]
+        .into_iter()
+        .map(|name| {
+            Logger::builder()
+                .with_address_port("127.0.0.1", 62000)
+                .with_app_name(name)
+                .with_version("0.3.1")
+                .with_async(1_000)
+                .build_local()
+                .with_context(|| format!("Failed to build logger for plugin {name}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    info!("Loaded plugins", "count" => plugins.len());
+    let handles = plugins
+        .into_iter()
+        .enumerate()
+        .map(|(i, logger)| {
+            std::thread::spawn(move || {
+                let job = span!(logger: logger, "process image {i}");
+                info!(logger: logger, "Started", "image" => i);
+                std::thread::sleep(Duration::from_millis(10));
+                warn!(logger: logger, "Image is {}x{}", 4000, 3000);
+                job.close();
+                logger.flush(Duration::from_secs(1))
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        let stats = handle.join().expect("Plugin thread panicked");
+        println!("Plugin logs: {stats}");
+    }
+
+    Ok(())
+}
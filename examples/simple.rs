@@ -9,13 +9,14 @@ fn main() {
 }
 
 fn try_main() -> Result<()> {
-    Logger::builder()
+    let guard = Logger::builder()
         .with_address_port("127.0.0.1", 62000)
         .build()
         .context("Failed to build logger")?;
 
     log!("Hello, world!");
 
-    println!("Log message sent successfully.");
+    let stats = guard.finish();
+    println!("Logs: {stats}");
     Ok(())
 }
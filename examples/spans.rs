@@ -0,0 +1,33 @@
+use anyhow::{Context, Result};
+use heimdall::{info, prelude::*, span, warn};
+
+fn main() {
+    if let Err(e) = try_main() {
+        eprintln!("Error: {e:?}");
+        std::process::exit(1);
+    }
+}
+
+fn try_main() -> Result<()> {
+    let _guard = Logger::builder()
+        .with_address_port("127.0.0.1", 62000)
+        .with_app_name("checkout")
+        .with_version("1.0.0")
+        .build()
+        .context("Failed to build logger")?;
+
+    let request = span!("handle request");
+    info!("Received order", "items" => 3);
+    {
+        let _query = span!("load cart");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    {
+        let _payment = span!("charge card");
+        warn!("Payment provider is slow", "latency_ms" => 850);
+    }
+    request.close();
+
+    println!("Log messages sent successfully.");
+    Ok(())
+}
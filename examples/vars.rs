@@ -9,7 +9,7 @@ fn main() {
 }
 
 fn try_main() -> Result<()> {
-    Logger::builder()
+    let _guard = Logger::builder()
         .with_address_port("127.0.0.1", 62000)
         .with_app_name("entity_processor")
         .with_version("1.0.0")
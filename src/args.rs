@@ -1,4 +1,7 @@
+use chrono::Duration;
 use clap::{Args as ClapArgs, Parser, Subcommand};
+use heimdall::storage::{Durability, RetentionPolicy, SqliteOptions};
+use std::time::Duration as StdDuration;
 
 const LONG_ABOUT: &str = "Heimdall watches your code for bugs.\nSee https://github.com/LeviLovie/heimdall for more info.";
 
@@ -47,6 +50,71 @@ pub struct ServerArgs {
         help = "Use a SQLite database at FILE (default logs.sqlite)"
     )]
     pub sqlite: Option<Option<String>>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = SqliteOptions::default().batch_size,
+        help = "Write SQLite logs in transactions of up to N records"
+    )]
+    pub batch_size: usize,
+
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = SqliteOptions::default().flush_interval.as_millis() as u64,
+        help = "Write a SQLite batch once its oldest log waited MS milliseconds"
+    )]
+    pub flush_interval: u64,
+
+    #[arg(
+        long,
+        value_name = "MODE",
+        default_value = "batched",
+        help = "batched: a crash may lose the unflushed batch, immediate: commit every log"
+    )]
+    pub durability: Durability,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Evict the oldest logs once more than N are stored"
+    )]
+    pub max_records: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "AGE",
+        value_parser = parse_age,
+        help = "Evict logs older than AGE, e.g. 90s, 30m, 12h, 7d"
+    )]
+    pub max_age: Option<Duration>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        help = "Evict the oldest logs once they take up more than SIZE, e.g. 500K, 64M, 2G. Quarantined messages are not counted"
+    )]
+    pub max_bytes: Option<u64>,
+}
+
+impl ServerArgs {
+    pub fn sqlite_options(&self) -> SqliteOptions {
+        SqliteOptions {
+            batch_size: self.batch_size.max(1),
+            flush_interval: StdDuration::from_millis(self.flush_interval),
+            durability: self.durability,
+        }
+    }
+
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_records: self.max_records,
+            max_age: self.max_age,
+            max_bytes: self.max_bytes,
+        }
+    }
 }
 
 #[derive(ClapArgs, Clone, Debug)]
@@ -60,3 +128,92 @@ pub struct PipeArgs {
     #[arg(long)]
     pub json: bool,
 }
+
+/// Splits a number from its unit suffix, e.g. "12h" into (12, "h")
+fn split_unit(s: &str) -> Result<(u64, String), String> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let number = s[..digits]
+        .parse()
+        .map_err(|_| format!("'{s}' does not start with a number"))?;
+    Ok((number, s[digits..].trim().to_lowercase()))
+}
+
+fn parse_age(s: &str) -> Result<Duration, String> {
+    let (number, unit) = split_unit(s)?;
+    let seconds = match unit.as_str() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Unknown age unit '{unit}', expected s, m, h, d or w"
+            ));
+        }
+    };
+    number
+        .checked_mul(seconds)
+        .and_then(|seconds| i64::try_from(seconds).ok())
+        .and_then(Duration::try_seconds)
+        .ok_or_else(|| format!("'{s}' is too long"))
+}
+
+fn parse_size(s: &str) -> Result<u64, String> {
+    let (number, unit) = split_unit(s)?;
+    let multiplier: u64 = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => return Err(format!("Unknown size unit '{unit}', expected K, M, G or T")),
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("'{s}' is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ages_are_parsed_with_units() {
+        assert_eq!(parse_age("90"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_age("30s"), Ok(Duration::seconds(30)));
+        assert_eq!(parse_age("5m"), Ok(Duration::minutes(5)));
+        assert_eq!(parse_age(" 2 H "), Ok(Duration::hours(2)));
+        assert_eq!(parse_age("7d"), Ok(Duration::days(7)));
+        assert_eq!(parse_age("1w"), Ok(Duration::weeks(1)));
+    }
+
+    #[test]
+    fn invalid_ages_are_rejected() {
+        for age in ["", "d", "-5m", "1.5h", "5y", "5 min"] {
+            assert!(parse_age(age).is_err(), "{age}");
+        }
+        assert!(parse_age("99999999999999999999").is_err());
+        assert!(parse_age("9999999999999999w").is_err());
+    }
+
+    #[test]
+    fn sizes_are_parsed_with_units() {
+        assert_eq!(parse_size("500"), Ok(500));
+        assert_eq!(parse_size("500B"), Ok(500));
+        assert_eq!(parse_size("500K"), Ok(500 << 10));
+        assert_eq!(parse_size("64m"), Ok(64 << 20));
+        assert_eq!(parse_size("2 GiB"), Ok(2 << 30));
+        assert_eq!(parse_size("1TB"), Ok(1 << 40));
+    }
+
+    #[test]
+    fn invalid_sizes_are_rejected() {
+        for size in ["", "K", "-1K", "1.5M", "5X", "5 KX"] {
+            assert!(parse_size(size).is_err(), "{size}");
+        }
+        assert!(parse_size("99999999999999999999").is_err());
+        assert!(parse_size("99999999T").is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Result, anyhow};
+use log::{LevelFilter, Metadata, Record, kv};
+
+use crate::log::STATIC_MIN_LEVEL;
+use crate::logger::{DEFAULT_FLUSH_TIMEOUT, global_logger};
+use crate::prelude::{Logger, RsLevel, RsLocation, RsValue, current_timestamp};
+
+pub mod prelude {
+    pub use super::LogFacade;
+}
+
+/// Backend for the `log` crate, forwarding its records to the global logger or to a logger of
+/// its own. Records keep their location, the target becomes a `target` var when it isn't the
+/// module and key-values become vars.
+pub struct LogFacade {
+    /// The global logger is used if not set
+    logger: Option<Logger>,
+    max_level: LevelFilter,
+}
+
+impl Default for LogFacade {
+    fn default() -> Self {
+        Self {
+            logger: None,
+            max_level: LevelFilter::Trace,
+        }
+    }
+}
+
+impl LogFacade {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forwards records to `logger` instead of the global logger
+    pub fn with_logger(mut self, logger: Logger) -> Self {
+        self.logger = Some(logger);
+        self
+    }
+
+    /// Records above `max_level` are skipped, `LevelFilter::Trace` by default
+    pub fn with_max_level(mut self, max_level: LevelFilter) -> Self {
+        self.max_level = max_level;
+        self
+    }
+
+    /// Installs the facade as the logger of the `log` crate, which can only be done once
+    pub fn init(self) -> Result<()> {
+        let max_level = self.max_level;
+        log::set_boxed_logger(Box::new(self))
+            .map_err(|_| anyhow!("A logger is already set for the log crate"))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    fn send(&self, record: &Record) -> Result<()> {
+        let level = level_from_log(record.level());
+        let module = record.module_path().unwrap_or(record.target());
+
+        let mut vars = Vars(Vec::new());
+        if record.target() != module {
+            vars.0
+                .push(("target".to_string(), RsValue::from(record.target())));
+        }
+        // Only fails if the visitor does, which it doesn't
+        let _ = record.key_values().visit(&mut vars);
+
+        let location = match (record.file(), record.line()) {
+            (Some(file), Some(line)) => Some(RsLocation {
+                file: file.to_string(),
+                line,
+                module: module.to_string(),
+                crate_name: module.split("::").next().unwrap_or(module).to_string(),
+            }),
+            _ => None,
+        };
+
+        let ts = current_timestamp();
+        let msg = record.args().to_string();
+        match &self.logger {
+            Some(logger) => logger.log(ts, level, msg, vars.0, location),
+            None => global_logger()?.log(ts, level, msg, vars.0, location),
+        }
+    }
+}
+
+impl log::Log for LogFacade {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.max_level
+            && level_from_log(metadata.level()) as u8 >= STATIC_MIN_LEVEL as u8
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.send(record).unwrap_or_else(|e| {
+            eprintln!("Failed to log message: {}", e);
+        });
+    }
+
+    fn flush(&self) {
+        match &self.logger {
+            Some(logger) => {
+                logger.flush(DEFAULT_FLUSH_TIMEOUT);
+            }
+            None => {
+                if let Ok(logger) = global_logger() {
+                    logger.flush(DEFAULT_FLUSH_TIMEOUT);
+                }
+            }
+        }
+    }
+}
+
+fn level_from_log(level: log::Level) -> RsLevel {
+    match level {
+        log::Level::Error => RsLevel::Error,
+        log::Level::Warn => RsLevel::Warn,
+        log::Level::Info => RsLevel::Info,
+        log::Level::Debug => RsLevel::Debug,
+        log::Level::Trace => RsLevel::Trace,
+    }
+}
+
+/// Collects the key-values of a record
+struct Vars(Vec<(String, RsValue)>);
+
+impl<'kvs> kv::VisitSource<'kvs> for Vars {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push((key.to_string(), value_from_log(&value)));
+        Ok(())
+    }
+}
+
+/// Keeps the type of primitive values, anything else is kept as its `Display` text. Integers
+/// are checked before floats since they also convert to `f64`.
+fn value_from_log(value: &kv::Value) -> RsValue {
+    if let Some(value) = value.to_bool() {
+        RsValue::Bool(value)
+    } else if let Some(value) = value.to_i64() {
+        RsValue::Int(value)
+    } else if let Some(value) = value.to_u64() {
+        RsValue::from(value)
+    } else if let Some(value) = value.to_f64() {
+        RsValue::Float(value)
+    } else if let Some(value) = value.to_borrowed_str() {
+        RsValue::from(value)
+    } else {
+        RsValue::Str(value.to_string())
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, FixedOffset, Local};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+use heimdall::prelude::*;
+
+/// Parses a request body that holds either a single log object or an array of them.
+pub fn logs_from_json(body: &Value, ip: &str) -> Result<Vec<RsLog>> {
+    match body {
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| log_from_json(item, ip).with_context(|| format!("Log #{i}")))
+            .collect(),
+        Value::Object(_) => Ok(vec![log_from_json(body, ip)?]),
+        _ => bail!("Expected a log object or an array of log objects"),
+    }
+}
+
+pub fn log_from_json(value: &Value, ip: &str) -> Result<RsLog> {
+    let obj = value
+        .as_object()
+        .ok_or_else(|| anyhow!("Log must be a JSON object"))?;
+
+    let ts: DateTime<FixedOffset> = match ["ts", "timestamp", "time"]
+        .iter()
+        .find_map(|key| obj.get(*key))
+    {
+        Some(Value::String(ts)) => DateTime::parse_from_rfc3339(ts)
+            .context("Failed to parse timestamp as a rfc3339 string")?,
+        Some(_) => bail!("Timestamp must be a rfc3339 string"),
+        None => Local::now().into(),
+    };
+
+    let msg = ["msg", "message"]
+        .iter()
+        .find_map(|key| obj.get(*key))
+        .ok_or_else(|| anyhow!("Log is missing a message"))?;
+    let msg = msg
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| msg.to_string());
+
+    let level = match obj.get("level") {
+        Some(Value::String(level)) => level.parse::<RsLevel>().map_err(|e| anyhow!(e))?,
+        Some(_) => bail!("Level must be a string"),
+        None => RsLevel::default(),
+    };
+
+    let pid = match obj.get("pid") {
+        Some(pid) => pid
+            .as_u64()
+            .and_then(|pid| u32::try_from(pid).ok())
+            .ok_or_else(|| anyhow!("Pid must be an unsigned 32-bit integer"))?,
+        None => 0,
+    };
+
+    let vars = match obj.get("vars") {
+        Some(Value::Object(vars)) => vars
+            .iter()
+            .map(|(key, val)| RsVar {
+                key: key.clone(),
+                val: value_from_json(val),
+            })
+            .collect(),
+        Some(_) => bail!("Vars must be a JSON object"),
+        None => Vec::new(),
+    };
+
+    let location = match obj.get("location") {
+        Some(Value::Object(location)) => Some(RsLocation {
+            file: string_field(location, "file"),
+            line: match location.get("line") {
+                Some(line) => line
+                    .as_u64()
+                    .and_then(|line| u32::try_from(line).ok())
+                    .ok_or_else(|| anyhow!("Line must be an unsigned 32-bit integer"))?,
+                None => 0,
+            },
+            module: string_field(location, "module"),
+            crate_name: string_field(location, "crate"),
+        }),
+        Some(Value::Null) | None => None,
+        Some(_) => bail!("Location must be a JSON object"),
+    };
+
+    let trace = match obj.get("trace_id") {
+        Some(Value::String(trace_id)) => Some(RsTrace {
+            trace_id: trace_id.clone(),
+            span_id: string_field(obj, "span_id"),
+            parent_span_id: obj
+                .get("parent_span_id")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        }),
+        Some(Value::Null) | None => None,
+        Some(_) => bail!("Trace id must be a string"),
+    };
+
+    let kind = match obj.get("kind").and_then(Value::as_str) {
+        Some("span") => RsKind::Span {
+            duration: Duration::from_micros(
+                obj.get("duration_us")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| anyhow!("Spans need a duration_us"))?,
+            ),
+        },
+        Some("event") | None => RsKind::Event,
+        Some(kind) => bail!("Unknown kind: {kind}"),
+    };
+
+    Ok(RsLog {
+        ts,
+        level,
+        msg,
+        ip: ip.to_string(),
+        context: RsContext {
+            app: string_field(obj, "app"),
+            pid,
+            os: string_field(obj, "os"),
+            version: string_field(obj, "version"),
+        },
+        vars,
+        location,
+        trace,
+        kind,
+    })
+}
+
+pub fn log_to_json(id: usize, log: &RsLog) -> Value {
+    let vars: serde_json::Map<String, Value> = log
+        .vars
+        .iter()
+        .map(|var| (var.key.clone(), value_to_json(&var.val)))
+        .collect();
+
+    json!({
+        "id": id,
+        "ts": log.ts.to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+        "level": log.level.as_str(),
+        "msg": log.msg,
+        "ip": log.ip,
+        "app": log.context.app,
+        "pid": log.context.pid,
+        "os": log.context.os,
+        "version": log.context.version,
+        "vars": vars,
+        "trace_id": log.trace.as_ref().map(|trace| &trace.trace_id),
+        "span_id": log.trace.as_ref().map(|trace| &trace.span_id),
+        "parent_span_id": log.trace.as_ref().and_then(|trace| trace.parent_span_id.as_ref()),
+        "kind": log.kind.as_str(),
+        "duration_us": log.kind.duration().map(|duration| duration.as_micros() as u64),
+        "location": log.location.as_ref().map(|location| json!({
+            "file": location.file,
+            "line": location.line,
+            "module": location.module,
+            "crate": location.crate_name,
+        })),
+    })
+}
+
+/// The raw bytes are hex encoded, they are not valid UTF-8 in general
+pub fn quarantined_to_json(message: &QuarantinedMessage) -> Value {
+    let raw: String = message
+        .raw
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    json!({
+        "ts": message.ts.to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+        "ip": message.ip,
+        "reason": message.reason,
+        "size": message.raw.len(),
+        "raw": raw,
+    })
+}
+
+/// Whole numbers become ints, other numbers floats. Nested values are kept as their JSON text.
+fn value_from_json(value: &Value) -> RsValue {
+    match value {
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => RsValue::Int(value),
+            None => RsValue::Float(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::Bool(value) => RsValue::Bool(*value),
+        Value::String(value) => RsValue::Str(value.clone()),
+        value => RsValue::Str(value.to_string()),
+    }
+}
+
+/// Bytes and timestamps are sent in their text form
+fn value_to_json(value: &RsValue) -> Value {
+    match value {
+        RsValue::Int(value) => Value::from(*value),
+        RsValue::Float(value) => Value::from(*value),
+        RsValue::Bool(value) => Value::from(*value),
+        value => Value::String(value.to_string()),
+    }
+}
+
+fn string_field(obj: &serde_json::Map<String, Value>, key: &str) -> String {
+    obj.get(key)
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string()
+}
@@ -1,15 +1,54 @@
-use anyhow::Result;
-use heimdall::status::ThreadType;
-use std::sync::{Arc, Mutex};
+mod json;
+mod query;
+
+use anyhow::{Context, Result, anyhow};
+use heimdall::{
+    prelude::{LogQuery, RsLog},
+    status::{ThreadStatus, ThreadType},
+};
+use serde_json::{Value, json};
+use std::{
+    io::{Cursor, Read, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    time::Duration,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
 
 use crate::data::Data;
 
-pub fn receive(data: Arc<Mutex<Data>>, _port: u16) -> Result<()> {
+const MAX_BODY_SIZE: u64 = 16 * 1024 * 1024;
+/// How long waiting for a request blocks before the thread checks whether it must terminate
+const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+/// Idle streams send a comment this often, which also detects disconnected clients
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// Each stream holds a thread, so further streams are refused beyond this
+const MAX_STREAMS: usize = 64;
+
+static OPEN_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn receive(data: Arc<Mutex<Data>>, port: u16) -> Result<()> {
+    let bind = format!("{}:{}", data.lock().unwrap().args.address, port);
     let print_info = !data.lock().unwrap().args.tui;
 
+    let server = Server::http(&bind)
+        .map_err(|e| anyhow!("{e}"))
+        .context("Failed to bind HTTP server to address")?;
+
+    if print_info {
+        println!("Listening for HTTP requests on {bind}");
+    }
+
     loop {
         let must_terminate = {
-            let data_lock = data.lock().unwrap();
+            let mut data_lock = data.lock().unwrap();
+            data_lock
+                .storage
+                .flush_if_due()
+                .context("Failed to flush storage")?;
             data_lock.statuses.must_terminate(ThreadType::HTTP)
         };
         if must_terminate {
@@ -18,7 +57,333 @@ pub fn receive(data: Arc<Mutex<Data>>, _port: u16) -> Result<()> {
             }
             break;
         }
+
+        match server.recv_timeout(RECV_TIMEOUT) {
+            Err(e) => println!(
+                "Error: {:?}",
+                anyhow!(e).context("Failed to receive request")
+            ),
+            Ok(None) => {}
+            Ok(Some(request)) => {
+                if let Err(e) = handle(&data, request, print_info) {
+                    println!("Error: {:?}", e.context("Failed to respond to request"));
+                }
+            }
+        }
+    }
+
+    // Streams may keep the storage alive well after this, so its drop can't be relied on
+    data.lock()
+        .unwrap()
+        .storage
+        .flush()
+        .context("Failed to flush storage")?;
+    Ok(())
+}
+
+fn handle(data: &Arc<Mutex<Data>>, mut request: Request, print_info: bool) -> Result<()> {
+    let (path, params) = match query::split_url(request.url()) {
+        Ok(split) => split,
+        Err(e) => {
+            return request
+                .respond(json_response(400, json!({ "error": format!("{e:#}") })))
+                .context("Failed to send response");
+        }
+    };
+
+    if *request.method() == Method::Get && path == "/logs/stream" {
+        return stream(data, request, &params);
+    }
+
+    let response = match (request.method(), path.as_str()) {
+        (Method::Post, "/logs") => ingest(data, &mut request, print_info),
+        (Method::Get, "/logs") => list(data, &params),
+        (Method::Get, "/logs/search") => search(data, &params),
+        (Method::Get, "/quarantine") => quarantine(data, &params),
+        (Method::Get, path) if path.starts_with("/traces/") => {
+            trace(data, &path["/traces/".len()..], &params)
+        }
+        (Method::Get, path) => match path.strip_prefix("/logs/").map(str::parse::<usize>) {
+            Some(Ok(id)) => get(data, id),
+            _ => json_response(404, json!({ "error": "Not found" })),
+        },
+        _ => json_response(404, json!({ "error": "Not found" })),
+    };
+
+    request.respond(response).context("Failed to send response")
+}
+
+fn ingest(
+    data: &Arc<Mutex<Data>>,
+    request: &mut Request,
+    print_info: bool,
+) -> Response<Cursor<Vec<u8>>> {
+    let ip = request
+        .remote_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default();
+
+    let mut body = String::new();
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_BODY_SIZE)
+        .read_to_string(&mut body)
+    {
+        return json_response(400, json!({ "error": format!("Failed to read body: {e}") }));
+    }
+
+    let logs = match serde_json::from_str::<Value>(&body)
+        .context("Failed to parse JSON body")
+        .and_then(|body| json::logs_from_json(&body, &ip))
+    {
+        Ok(logs) => logs,
+        Err(e) => return json_response(400, json!({ "error": format!("{e:#}") })),
+    };
+
+    let accepted = logs.len();
+    let mut data = data.lock().unwrap();
+    for log in logs {
+        if print_info {
+            println!("{log}");
+        }
+        if let Err(e) = data.storage.add_log(log) {
+            return json_response(500, json!({ "error": format!("Failed to store log: {e}") }));
+        }
+    }
+
+    json_response(201, json!({ "accepted": accepted }))
+}
+
+fn list(data: &Arc<Mutex<Data>>, params: &[(String, String)]) -> Response<Cursor<Vec<u8>>> {
+    let (log_query, page) = match query::log_query_from_params(params)
+        .and_then(|log_query| Ok((log_query, query::page_from_params(params)?)))
+    {
+        Ok(parsed) => parsed,
+        Err(e) => return json_response(400, json!({ "error": format!("{e:#}") })),
+    };
+
+    let data = data.lock().unwrap();
+    let result = data.storage.count(&log_query).and_then(|total| {
+        let logs = data.storage.query(&log_query, page.offset, page.limit)?;
+        Ok((total, logs))
+    });
+
+    match result {
+        Ok((total, logs)) => json_response(
+            200,
+            json!({
+                "total": total,
+                "offset": page.offset,
+                "limit": page.limit,
+                "logs": logs
+                    .iter()
+                    .map(|(id, log)| json::log_to_json(*id, log))
+                    .collect::<Vec<_>>(),
+            }),
+        ),
+        Err(e) => json_response(
+            500,
+            json!({ "error": format!("Failed to query logs: {e}") }),
+        ),
+    }
+}
+
+fn search(data: &Arc<Mutex<Data>>, params: &[(String, String)]) -> Response<Cursor<Vec<u8>>> {
+    let Some((_, search_query)) = params.iter().find(|(key, _)| key == "q") else {
+        return json_response(400, json!({ "error": "Missing search query parameter q" }));
+    };
+    let page = match query::page_from_params(params) {
+        Ok(page) => page,
+        Err(e) => return json_response(400, json!({ "error": format!("{e:#}") })),
+    };
+
+    let data = data.lock().unwrap();
+    let result = data
+        .storage
+        .search(search_query, page.offset, page.limit)
+        .and_then(|ids| {
+            let mut logs = Vec::with_capacity(ids.len());
+            for id in ids {
+                if let Some(log) = data.storage.get_log_by_id(id)? {
+                    logs.push(json::log_to_json(id, &log));
+                }
+            }
+            Ok(logs)
+        });
+
+    match result {
+        Ok(logs) => json_response(
+            200,
+            json!({
+                "query": search_query,
+                "offset": page.offset,
+                "limit": page.limit,
+                "logs": logs,
+            }),
+        ),
+        Err(e) => json_response(
+            500,
+            json!({ "error": format!("Failed to search logs: {e}") }),
+        ),
+    }
+}
+
+/// Rejected messages per peer and the most recent quarantined ones
+fn quarantine(data: &Arc<Mutex<Data>>, params: &[(String, String)]) -> Response<Cursor<Vec<u8>>> {
+    let page = match query::page_from_params(params) {
+        Ok(page) => page,
+        Err(e) => return json_response(400, json!({ "error": format!("{e:#}") })),
+    };
+
+    let data = data.lock().unwrap();
+    match data.storage.quarantined(page.offset, page.limit) {
+        Ok(messages) => json_response(
+            200,
+            json!({
+                "rejected": data.storage.rejected(),
+                "messages": messages.iter().map(json::quarantined_to_json).collect::<Vec<_>>(),
+            }),
+        ),
+        Err(e) => json_response(
+            500,
+            json!({ "error": format!("Failed to read quarantine: {e}") }),
+        ),
+    }
+}
+
+/// A page of the logs of a trace, oldest first. `truncated` tells whether the trace goes on past
+/// this page.
+fn trace(
+    data: &Arc<Mutex<Data>>,
+    trace_id: &str,
+    params: &[(String, String)],
+) -> Response<Cursor<Vec<u8>>> {
+    let page = match query::page_from_params(params) {
+        Ok(page) => page,
+        Err(e) => return json_response(400, json!({ "error": format!("{e:#}") })),
+    };
+
+    match data
+        .lock()
+        .unwrap()
+        .storage
+        .get_trace(trace_id, page.offset, page.limit)
+    {
+        Ok(trace) if trace.total == 0 => json_response(
+            404,
+            json!({ "error": format!("No logs in trace {trace_id}") }),
+        ),
+        Ok(trace) => json_response(
+            200,
+            json!({
+                "trace_id": trace_id,
+                "total": trace.total,
+                "offset": page.offset,
+                "limit": page.limit,
+                "truncated": page.offset.saturating_add(trace.logs.len()) < trace.total,
+                "logs": trace
+                    .logs
+                    .iter()
+                    .map(|(id, log)| json::log_to_json(*id, log))
+                    .collect::<Vec<_>>(),
+            }),
+        ),
+        Err(e) => json_response(500, json!({ "error": format!("Failed to get trace: {e}") })),
+    }
+}
+
+fn get(data: &Arc<Mutex<Data>>, id: usize) -> Response<Cursor<Vec<u8>>> {
+    match data.lock().unwrap().storage.get_log_by_id(id) {
+        Ok(Some(log)) => json_response(200, json::log_to_json(id, &log)),
+        Ok(None) => json_response(404, json!({ "error": format!("No log with id {id}") })),
+        Err(e) => json_response(500, json!({ "error": format!("Failed to get log: {e}") })),
+    }
+}
+
+/// Answers with a Server-Sent Events stream of newly stored logs on a separate thread
+fn stream(data: &Arc<Mutex<Data>>, request: Request, params: &[(String, String)]) -> Result<()> {
+    let log_query = match query::log_query_from_params(params) {
+        Ok(log_query) => log_query,
+        Err(e) => {
+            return request
+                .respond(json_response(400, json!({ "error": format!("{e:#}") })))
+                .context("Failed to send response");
+        }
+    };
+
+    let reserved = OPEN_STREAMS
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+            (open < MAX_STREAMS).then_some(open + 1)
+        })
+        .is_ok();
+    if !reserved {
+        return request
+            .respond(json_response(
+                503,
+                json!({ "error": format!("Too many open streams, at most {MAX_STREAMS} are served") }),
+            ))
+            .context("Failed to send response");
     }
 
+    let receiver = data.lock().unwrap().storage.subscribe();
+    let data = data.clone();
+    let writer = request.into_writer();
+
+    std::thread::spawn(move || {
+        // The client is gone once writing fails, nothing to report
+        let _ = write_stream(&data, writer, receiver, &log_query);
+        OPEN_STREAMS.fetch_sub(1, Ordering::SeqCst);
+    });
+
     Ok(())
 }
+
+fn write_stream(
+    data: &Arc<Mutex<Data>>,
+    mut writer: Box<dyn Write + Send>,
+    receiver: Receiver<(usize, RsLog)>,
+    log_query: &LogQuery,
+) -> std::io::Result<()> {
+    writer.write_all(
+        b"HTTP/1.1 200 OK\r\n\
+          Content-Type: text/event-stream\r\n\
+          Cache-Control: no-cache\r\n\
+          Connection: close\r\n\r\n",
+    )?;
+    writer.flush()?;
+
+    loop {
+        match receiver.recv_timeout(KEEPALIVE_INTERVAL) {
+            Ok((id, log)) => {
+                if log_query.matches(&log) {
+                    let event = json::log_to_json(id, &log);
+                    write!(writer, "id: {id}\ndata: {event}\n\n")?;
+                    writer.flush()?;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                let running = matches!(
+                    data.lock().unwrap().statuses.get(ThreadType::HTTP),
+                    Some(ThreadStatus::Running)
+                );
+                if !running {
+                    break;
+                }
+                writer.write_all(b": keepalive\n\n")?;
+                writer.flush()?;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Ok(())
+}
+
+fn json_response(status: u16, body: Value) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(
+            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                .expect("Static header is valid"),
+        )
+}
@@ -0,0 +1,285 @@
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::DateTime;
+use regex::Regex;
+use std::collections::HashSet;
+
+use heimdall::prelude::*;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// Splits a request url into its path and decoded query parameters
+pub fn split_url(url: &str) -> Result<(String, Vec<(String, String)>)> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, val) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key)?, percent_decode(val)?))
+        })
+        .collect::<Result<_>>()?;
+    Ok((percent_decode(path)?, params))
+}
+
+/// Fails on a `%` that isn't followed by two hex digits and on escapes that aren't UTF-8
+fn percent_decode(input: &str) -> Result<String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |offset: usize| {
+            bytes
+                .get(i + offset)
+                .and_then(|byte| (*byte as char).to_digit(16))
+        };
+        match (bytes[i], hex(1), hex(2)) {
+            (b'%', Some(high), Some(low)) => {
+                out.push((high * 16 + low) as u8);
+                i += 2;
+            }
+            (b'%', _, _) => bail!("Invalid percent escape in url: {input}"),
+            (b'+', _, _) => out.push(b' '),
+            (byte, _, _) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8(out).map_err(|_| anyhow!("Url escapes are not valid UTF-8: {input}"))
+}
+
+/// Every parameter but `var` may only be given once
+fn check_repeated(params: &[(String, String)]) -> Result<()> {
+    let mut seen = HashSet::new();
+    for (key, _) in params {
+        if key != "var" && !seen.insert(key) {
+            bail!("Query parameter {key} is given more than once");
+        }
+    }
+    Ok(())
+}
+
+/// Pagination requested by the client
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+pub fn page_from_params(params: &[(String, String)]) -> Result<Page> {
+    check_repeated(params)?;
+    let mut page = Page {
+        offset: 0,
+        limit: DEFAULT_LIMIT,
+    };
+    for (key, val) in params {
+        match key.as_str() {
+            "offset" => page.offset = val.parse().context("Offset must be a number")?,
+            "limit" => {
+                page.limit = val
+                    .parse::<usize>()
+                    .context("Limit must be a number")?
+                    .min(MAX_LIMIT)
+            }
+            _ => {}
+        }
+    }
+    Ok(page)
+}
+
+pub fn log_query_from_params(params: &[(String, String)]) -> Result<LogQuery> {
+    check_repeated(params)?;
+    let mut query = LogQuery::default();
+    for (key, val) in params {
+        match key.as_str() {
+            "from" => {
+                query.from = Some(
+                    DateTime::parse_from_rfc3339(val)
+                        .context("From must be a rfc3339 timestamp")?,
+                )
+            }
+            "to" => {
+                query.to = Some(
+                    DateTime::parse_from_rfc3339(val).context("To must be a rfc3339 timestamp")?,
+                )
+            }
+            "app" => query.app = Some(val.clone()),
+            "version" => query.version = Some(val.clone()),
+            "pid" => query.pid = Some(val.parse().context("Pid must be a number")?),
+            "ip" => query.ip = Some(val.clone()),
+            "msg" => query.msg = Some(MessageFilter::Contains(val.clone())),
+            "msg_regex" => {
+                query.msg = Some(MessageFilter::Regex(
+                    Regex::new(val).context("Invalid message regex")?,
+                ))
+            }
+            "level" => query.level = Some(val.parse::<RsLevel>().map_err(|e| anyhow!(e))?),
+            "var" => query.vars.push(var_filter_from_param(val)?),
+            "file" => query.file = Some(val.clone()),
+            "line" => query.line = Some(val.parse().context("Line must be a number")?),
+            "module" => query.module = Some(val.clone()),
+            "crate" => query.crate_name = Some(val.clone()),
+            "trace_id" => query.trace_id = Some(val.clone()),
+            "offset" | "limit" => {}
+            _ => bail!("Unknown query parameter: {key}"),
+        }
+    }
+    Ok(query)
+}
+
+/// Parses `key=value`, `key<value`, `key<=value`, `key>value` or `key>=value`
+fn var_filter_from_param(param: &str) -> Result<VarFilter> {
+    let Some(split) = param.find(['=', '<', '>']) else {
+        bail!("Var filters must look like key=value, key<value or key>value");
+    };
+    let (key, rest) = param.split_at(split);
+    let (op, value) = if let Some(value) = rest.strip_prefix("<=") {
+        (VarOp::Le, value)
+    } else if let Some(value) = rest.strip_prefix(">=") {
+        (VarOp::Ge, value)
+    } else if let Some(value) = rest.strip_prefix('<') {
+        (VarOp::Lt, value)
+    } else if let Some(value) = rest.strip_prefix('>') {
+        (VarOp::Gt, value)
+    } else {
+        (VarOp::Eq, &rest[1..])
+    };
+    if key.is_empty() {
+        bail!("Var filters need a key");
+    }
+    if op != VarOp::Eq && value.is_empty() {
+        bail!("Var filters need a value to compare with");
+    }
+    Ok(VarFilter::new(key, op, parse_var_value(value)))
+}
+
+/// Guesses the type of a value given in a url, anything that isn't a number, a boolean or a
+/// rfc3339 timestamp is a string
+fn parse_var_value(value: &str) -> RsValue {
+    if let Ok(value) = value.parse::<i64>() {
+        RsValue::Int(value)
+    } else if let Some(value) = value.parse::<f64>().ok().filter(|value| value.is_finite()) {
+        // "nan" and "inf" parse as floats but are more likely meant as text
+        RsValue::Float(value)
+    } else if let Ok(value) = value.parse::<bool>() {
+        RsValue::Bool(value)
+    } else if let Ok(value) = DateTime::parse_from_rfc3339(value) {
+        RsValue::Timestamp(value)
+    } else {
+        RsValue::Str(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, val)| (key.to_string(), val.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn urls_are_decoded() {
+        let (path, params) = split_url("/logs/%73tream?msg=a+b%20c&app=%E2%9C%93&flag").unwrap();
+        assert_eq!(path, "/logs/stream");
+        assert_eq!(
+            params,
+            [
+                ("msg".to_string(), "a b c".to_string()),
+                ("app".to_string(), "✓".to_string()),
+                ("flag".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(split_url("/logs?var=n%3E%3D5").unwrap().1[0].1, "n>=5");
+    }
+
+    #[test]
+    fn invalid_escapes_are_rejected() {
+        for url in [
+            "/logs?msg=%",
+            "/logs?msg=%4",
+            "/logs?msg=%zz",
+            "/lo%gs",
+            "/logs?m%sg=a",
+            "/logs?msg=%ff",
+        ] {
+            assert!(split_url(url).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn repeated_params_are_rejected() {
+        assert!(log_query_from_params(&params(&[("app", "a"), ("app", "b")])).is_err());
+        assert!(page_from_params(&params(&[("limit", "1"), ("limit", "2")])).is_err());
+
+        let query = log_query_from_params(&params(&[("var", "a=1"), ("var", "b<2")])).unwrap();
+        assert_eq!(query.vars.len(), 2);
+    }
+
+    #[test]
+    fn params_become_a_query() {
+        let query = log_query_from_params(&params(&[
+            ("app", "api"),
+            ("pid", "42"),
+            ("level", "warn"),
+            ("from", "2024-05-01T12:00:00Z"),
+            ("offset", "10"),
+        ]))
+        .unwrap();
+        assert_eq!(query.app.as_deref(), Some("api"));
+        assert_eq!(query.pid, Some(42));
+        assert_eq!(query.level, Some(RsLevel::Warn));
+        assert!(query.from.is_some());
+
+        for (key, val) in [
+            ("pid", "-1"),
+            ("level", "loud"),
+            ("from", "yesterday"),
+            ("msg_regex", "("),
+            ("line", "x"),
+            ("unknown", "1"),
+        ] {
+            assert!(
+                log_query_from_params(&params(&[(key, val)])).is_err(),
+                "{key}"
+            );
+        }
+        assert!(page_from_params(&params(&[("offset", "-1")])).is_err());
+    }
+
+    #[test]
+    fn var_filters_are_parsed() {
+        let filter = var_filter_from_param("n>=5").unwrap();
+        assert_eq!((filter.key.as_str(), filter.op), ("n", VarOp::Ge));
+        assert_eq!(filter.value, RsValue::Int(5));
+
+        let filter = var_filter_from_param("name=a=b").unwrap();
+        assert_eq!((filter.key.as_str(), filter.op), ("name", VarOp::Eq));
+        assert_eq!(filter.value, RsValue::Str("a=b".to_string()));
+
+        assert_eq!(
+            var_filter_from_param("f<1.5").unwrap().value,
+            RsValue::Float(1.5)
+        );
+        assert_eq!(
+            var_filter_from_param("ok=true").unwrap().value,
+            RsValue::Bool(true)
+        );
+        assert_eq!(
+            var_filter_from_param("x=nan").unwrap().value,
+            RsValue::Str("nan".to_string())
+        );
+        assert_eq!(
+            var_filter_from_param("empty=").unwrap().value,
+            RsValue::Str(String::new())
+        );
+    }
+
+    #[test]
+    fn malformed_var_filters_are_rejected() {
+        for param in ["", "key", "=5", "<5", ">=", "n<", "n>="] {
+            assert!(var_filter_from_param(param).is_err(), "{param}");
+        }
+    }
+}
@@ -1,15 +1,21 @@
+#[cfg(feature = "log")]
+pub mod facade;
 pub mod log;
 pub mod logger;
 pub mod macros;
 pub mod schemas;
+pub mod span;
 pub mod status;
 pub mod storage;
 
 pub mod prelude {
     use super::*;
 
-    pub use log::prelude::*;
+    pub use super::log::prelude::*;
+    #[cfg(feature = "log")]
+    pub use facade::prelude::*;
     pub use logger::prelude::*;
+    pub use span::prelude::*;
     pub use status::prelude::*;
     pub use storage::prelude::*;
 }
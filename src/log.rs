@@ -1,18 +1,468 @@
 use chrono::{DateTime, FixedOffset};
+use flatbuffers::{FlatBufferBuilder, InvalidFlatbuffer, VerifierOptions, WIPOffset};
+use std::time::Duration;
 
-use crate::schemas::log::log::{Context, ContextArgs, Log, LogArgs, Var, VarArgs};
+use crate::schemas::log::log::{
+    BoolValue, BoolValueArgs, BytesValue, BytesValueArgs, Context, ContextArgs, FloatValue,
+    FloatValueArgs, IntValue, IntValueArgs, Kind, Level, Location, LocationArgs, Log, LogArgs,
+    LogBatch, LogBatchArgs, TimestampValue, TimestampValueArgs, Value, Var, VarArgs,
+};
 
 pub mod prelude {
-    pub use super::{RsContext, RsLog, RsVar};
+    pub use super::{
+        DecodeError, RsContext, RsKind, RsLevel, RsLocation, RsLog, RsTrace, RsValue, RsVar,
+        ToRsValue,
+    };
+}
+
+/// Largest encoded log that is accepted
+pub const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Limits for verifying incoming logs, a log is a few tables deep and has one table per var
+const VERIFIER_OPTIONS: VerifierOptions = VerifierOptions {
+    max_depth: 8,
+    max_tables: 16 * 1024,
+    max_apparent_size: MAX_MESSAGE_SIZE,
+    ignore_missing_null_terminator: false,
+};
+
+/// A batch holds any number of logs, so only the message size bounds its table count
+const BATCH_VERIFIER_OPTIONS: VerifierOptions = VerifierOptions {
+    max_depth: 9,
+    max_tables: MAX_MESSAGE_SIZE,
+    max_apparent_size: MAX_MESSAGE_SIZE,
+    ignore_missing_null_terminator: false,
+};
+
+/// File identifier of `LogBatch` messages, single logs are sent without one
+const BATCH_IDENTIFIER: &str = "HBAT";
+
+/// Least severe level that the logging macros compile in, selected with the
+/// `max_level_*` cargo features. When several are enabled the strictest one wins.
+pub const STATIC_MIN_LEVEL: RsLevel = if cfg!(feature = "max_level_fatal") {
+    RsLevel::Fatal
+} else if cfg!(feature = "max_level_error") {
+    RsLevel::Error
+} else if cfg!(feature = "max_level_warn") {
+    RsLevel::Warn
+} else if cfg!(feature = "max_level_info") {
+    RsLevel::Info
+} else if cfg!(feature = "max_level_debug") {
+    RsLevel::Debug
+} else {
+    RsLevel::Trace
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(u8)]
+pub enum RsLevel {
+    Trace,
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl RsLevel {
+    pub const ALL: [RsLevel; 6] = [
+        RsLevel::Trace,
+        RsLevel::Debug,
+        RsLevel::Info,
+        RsLevel::Warn,
+        RsLevel::Error,
+        RsLevel::Fatal,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RsLevel::Trace => "trace",
+            RsLevel::Debug => "debug",
+            RsLevel::Info => "info",
+            RsLevel::Warn => "warn",
+            RsLevel::Error => "error",
+            RsLevel::Fatal => "fatal",
+        }
+    }
+
+    pub fn as_i64(&self) -> i64 {
+        *self as i64
+    }
+
+    pub fn from_i64(value: i64) -> Option<Self> {
+        Self::ALL.get(usize::try_from(value).ok()?).copied()
+    }
+
+    /// The verifier doesn't check enum ranges, so values outside of the schema are rejected here
+    fn from_schema(level: Level) -> Result<Self, DecodeError> {
+        Ok(match level {
+            Level::Trace => RsLevel::Trace,
+            Level::Debug => RsLevel::Debug,
+            Level::Info => RsLevel::Info,
+            Level::Warn => RsLevel::Warn,
+            Level::Error => RsLevel::Error,
+            Level::Fatal => RsLevel::Fatal,
+            Level(value) => {
+                return Err(DecodeError::OutOfRange {
+                    field: "level",
+                    value,
+                });
+            }
+        })
+    }
+
+    fn to_schema(self) -> Level {
+        match self {
+            RsLevel::Trace => Level::Trace,
+            RsLevel::Debug => Level::Debug,
+            RsLevel::Info => Level::Info,
+            RsLevel::Warn => Level::Warn,
+            RsLevel::Error => Level::Error,
+            RsLevel::Fatal => Level::Fatal,
+        }
+    }
+}
+
+impl std::str::FromStr for RsLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("Unknown log level: {s}"))
+    }
+}
+
+impl std::fmt::Display for RsLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why a received message is not a valid log
+#[derive(Debug)]
+pub enum DecodeError {
+    TooLarge { size: usize },
+    Invalid(InvalidFlatbuffer),
+    MissingTimestamp,
+    BadTimestamp(chrono::ParseError),
+    OutOfRange { field: &'static str, value: i8 },
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::TooLarge { size } => write!(
+                f,
+                "Message of {size} bytes exceeds the limit of {MAX_MESSAGE_SIZE} bytes"
+            ),
+            DecodeError::Invalid(e) => {
+                write!(f, "Invalid FlatBuffer: {}", e.to_string().trim_end())
+            }
+            DecodeError::MissingTimestamp => write!(f, "Log is missing a timestamp"),
+            DecodeError::BadTimestamp(e) => write!(f, "Failed to parse timestamp: {e}"),
+            DecodeError::OutOfRange { field, value } => {
+                write!(f, "Value {value} of {field} is out of range")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// The value of a var, keeping the type it was logged with
+#[derive(Debug, Clone, PartialEq)]
+pub enum RsValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Bytes(Vec<u8>),
+    Timestamp(DateTime<FixedOffset>),
+}
+
+impl RsValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            RsValue::Int(_) => "int",
+            RsValue::Float(_) => "float",
+            RsValue::Bool(_) => "bool",
+            RsValue::Str(_) => "string",
+            RsValue::Bytes(_) => "bytes",
+            RsValue::Timestamp(_) => "timestamp",
+        }
+    }
+
+    /// Orders numbers with numbers, timestamps with timestamps and strings with strings
+    pub fn compare(&self, other: &RsValue) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (RsValue::Int(a), RsValue::Int(b)) => Some(a.cmp(b)),
+            (RsValue::Int(_) | RsValue::Float(_), RsValue::Int(_) | RsValue::Float(_)) => {
+                self.as_f64()?.partial_cmp(&other.as_f64()?)
+            }
+            (RsValue::Timestamp(a), RsValue::Timestamp(b)) => Some(a.cmp(b)),
+            (RsValue::Str(a), RsValue::Str(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            RsValue::Int(value) => Some(*value as f64),
+            RsValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn from_schema(var: &Var<'_>) -> Self {
+        let text = || var.val().unwrap_or("").to_string();
+        match var.value_type() {
+            Value::IntValue => var
+                .value_as_int_value()
+                .map(|value| RsValue::Int(value.value())),
+            Value::FloatValue => var
+                .value_as_float_value()
+                .map(|value| RsValue::Float(value.value())),
+            Value::BoolValue => var
+                .value_as_bool_value()
+                .map(|value| RsValue::Bool(value.value())),
+            Value::BytesValue => var.value_as_bytes_value().map(|value| {
+                RsValue::Bytes(
+                    value
+                        .value()
+                        .map(|bytes| bytes.bytes().to_vec())
+                        .unwrap_or_default(),
+                )
+            }),
+            Value::TimestampValue => var
+                .value_as_timestamp_value()
+                .and_then(|value| DateTime::parse_from_rfc3339(value.value()?).ok())
+                .map(RsValue::Timestamp),
+            // Strings, and types from newer clients, fall back to the text form
+            _ => None,
+        }
+        .unwrap_or_else(|| RsValue::Str(text()))
+    }
+}
+
+impl std::fmt::Display for RsValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RsValue::Int(value) => write!(f, "{value}"),
+            RsValue::Float(value) => write!(f, "{value}"),
+            RsValue::Bool(value) => write!(f, "{value}"),
+            RsValue::Str(value) => f.write_str(value),
+            RsValue::Bytes(bytes) => bytes.iter().try_for_each(|byte| write!(f, "{byte:02x}")),
+            RsValue::Timestamp(ts) => {
+                f.write_str(&ts.to_rfc3339_opts(chrono::SecondsFormat::Micros, true))
+            }
+        }
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($t:ty),*) => {
+        $(impl From<$t> for RsValue {
+            fn from(value: $t) -> Self {
+                RsValue::Int(value.into())
+            }
+        })*
+    };
+}
+
+impl_from_int!(i8, i16, i32, i64, u8, u16, u32);
+
+macro_rules! impl_from_wide_int {
+    ($($t:ty),*) => {
+        $(impl From<$t> for RsValue {
+            /// Values that don't fit in an `i64` are kept as text
+            fn from(value: $t) -> Self {
+                i64::try_from(value)
+                    .map(RsValue::Int)
+                    .unwrap_or_else(|_| RsValue::Str(value.to_string()))
+            }
+        })*
+    };
+}
+
+impl_from_wide_int!(isize, u64, usize, i128, u128);
+
+impl From<f32> for RsValue {
+    fn from(value: f32) -> Self {
+        RsValue::Float(value.into())
+    }
+}
+
+impl From<f64> for RsValue {
+    fn from(value: f64) -> Self {
+        RsValue::Float(value)
+    }
+}
+
+impl From<bool> for RsValue {
+    fn from(value: bool) -> Self {
+        RsValue::Bool(value)
+    }
+}
+
+impl From<char> for RsValue {
+    fn from(value: char) -> Self {
+        RsValue::Str(value.to_string())
+    }
+}
+
+impl From<&str> for RsValue {
+    fn from(value: &str) -> Self {
+        RsValue::Str(value.to_string())
+    }
+}
+
+impl From<String> for RsValue {
+    fn from(value: String) -> Self {
+        RsValue::Str(value)
+    }
+}
+
+impl From<&String> for RsValue {
+    fn from(value: &String) -> Self {
+        RsValue::Str(value.clone())
+    }
+}
+
+impl From<Vec<u8>> for RsValue {
+    fn from(value: Vec<u8>) -> Self {
+        RsValue::Bytes(value)
+    }
+}
+
+impl From<&[u8]> for RsValue {
+    fn from(value: &[u8]) -> Self {
+        RsValue::Bytes(value.to_vec())
+    }
+}
+
+impl<Tz: chrono::TimeZone> From<DateTime<Tz>> for RsValue {
+    fn from(value: DateTime<Tz>) -> Self {
+        RsValue::Timestamp(value.fixed_offset())
+    }
+}
+
+/// Durations are logged as fractional seconds
+impl From<Duration> for RsValue {
+    fn from(value: Duration) -> Self {
+        RsValue::Float(value.as_secs_f64())
+    }
+}
+
+/// Values that keep their type when passed to the logging macros as a var. Anything else that
+/// implements `Display` is logged as text.
+pub trait ToRsValue {
+    fn to_rs_value(&self) -> RsValue;
+}
+
+macro_rules! impl_to_rs_value {
+    ($($t:ty),*) => {
+        $(impl ToRsValue for $t {
+            fn to_rs_value(&self) -> RsValue {
+                RsValue::from(self.clone())
+            }
+        })*
+    };
+}
+
+impl_to_rs_value!(
+    i8,
+    i16,
+    i32,
+    i64,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
+    i128,
+    u128,
+    f32,
+    f64,
+    bool,
+    char,
+    String,
+    Vec<u8>,
+    Duration
+);
+
+impl ToRsValue for str {
+    fn to_rs_value(&self) -> RsValue {
+        RsValue::from(self)
+    }
+}
+
+impl ToRsValue for [u8] {
+    fn to_rs_value(&self) -> RsValue {
+        RsValue::from(self)
+    }
+}
+
+impl<Tz: chrono::TimeZone> ToRsValue for DateTime<Tz> {
+    fn to_rs_value(&self) -> RsValue {
+        RsValue::from(self.clone())
+    }
+}
+
+impl ToRsValue for RsValue {
+    fn to_rs_value(&self) -> RsValue {
+        self.clone()
+    }
+}
+
+impl<T: ToRsValue + ?Sized> ToRsValue for &T {
+    fn to_rs_value(&self) -> RsValue {
+        (**self).to_rs_value()
+    }
+}
+
+/// Used by the logging macros to pick `ToRsValue` for a var when it is implemented and
+/// `Display` otherwise. Method lookup tries `Typed` on `VarValue` before it tries `Displayed`
+/// on a reference to it.
+#[doc(hidden)]
+pub mod __private {
+    use super::{RsValue, ToRsValue};
+    use std::fmt::Display;
+
+    pub struct VarValue<'a, T: ?Sized>(pub &'a T);
+
+    pub trait Typed {
+        fn rs_value(&self) -> RsValue;
+    }
+
+    impl<T: ToRsValue + ?Sized> Typed for VarValue<'_, T> {
+        fn rs_value(&self) -> RsValue {
+            self.0.to_rs_value()
+        }
+    }
+
+    pub trait Displayed {
+        fn rs_value(&self) -> RsValue;
+    }
+
+    impl<T: Display + ?Sized> Displayed for &VarValue<'_, T> {
+        fn rs_value(&self) -> RsValue {
+            RsValue::Str(self.0.to_string())
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct RsVar {
     pub key: String,
-    pub val: String,
+    pub val: RsValue,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct RsContext {
     pub app: String,
     pub pid: u32,
@@ -20,21 +470,77 @@ pub struct RsContext {
     pub version: String,
 }
 
+/// Source location of the logging call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsLocation {
+    pub file: String,
+    pub line: u32,
+    pub module: String,
+    pub crate_name: String,
+}
+
+impl std::fmt::Display for RsLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Where a log sits in a distributed trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsTrace {
+    pub trace_id: String,
+    /// The span the log was made in, or for spans the span itself
+    pub span_id: String,
+    /// Only set for spans that were opened inside another span
+    pub parent_span_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RsKind {
+    #[default]
+    Event,
+    /// A closed span, sent once with how long it was open
+    Span { duration: Duration },
+}
+
+impl RsKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RsKind::Event => "event",
+            RsKind::Span { .. } => "span",
+        }
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            RsKind::Event => None,
+            RsKind::Span { duration } => Some(*duration),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RsLog {
     pub ts: DateTime<FixedOffset>,
+    pub level: RsLevel,
     pub msg: String,
     pub ip: String,
     pub context: RsContext,
     pub vars: Vec<RsVar>,
+    /// Missing for logs that were not sent through the logging macros
+    pub location: Option<RsLocation>,
+    pub trace: Option<RsTrace>,
+    pub kind: RsKind,
 }
 
 impl RsLog {
     pub fn new(
         ts: DateTime<FixedOffset>,
+        level: RsLevel,
         msg: String,
         context: RsContext,
-        vars: Vec<(String, String)>,
+        vars: Vec<(String, RsValue)>,
+        location: Option<RsLocation>,
     ) -> Self {
         let vars = vars
             .into_iter()
@@ -43,29 +549,92 @@ impl RsLog {
 
         Self {
             ts,
+            level,
             msg,
             ip: String::new(),
             context,
             vars,
+            location,
+            trace: None,
+            kind: RsKind::Event,
         }
     }
 
-    pub fn from(log: Log<'_>, ip: String) -> Self {
+    /// Verifies and decodes a log received from `ip`
+    pub fn decode(buf: &[u8], ip: String) -> Result<Self, DecodeError> {
+        if buf.len() > MAX_MESSAGE_SIZE {
+            return Err(DecodeError::TooLarge { size: buf.len() });
+        }
+        let log = flatbuffers::root_with_opts::<Log>(&VERIFIER_OPTIONS, buf)
+            .map_err(DecodeError::Invalid)?;
+        Self::from_log(log, ip)
+    }
+
+    /// Verifies and decodes a message holding either a single log or a `LogBatch`. A batch is
+    /// rejected as a whole if any of its logs is.
+    pub fn decode_message(buf: &[u8], ip: String) -> Result<Vec<Self>, DecodeError> {
+        if buf.get(4..8) != Some(BATCH_IDENTIFIER.as_bytes()) {
+            return Ok(vec![Self::decode(buf, ip)?]);
+        }
+        if buf.len() > MAX_MESSAGE_SIZE {
+            return Err(DecodeError::TooLarge { size: buf.len() });
+        }
+        let batch = flatbuffers::root_with_opts::<LogBatch>(&BATCH_VERIFIER_OPTIONS, buf)
+            .map_err(DecodeError::Invalid)?;
+        batch
+            .logs()
+            .unwrap_or_default()
+            .iter()
+            .map(|log| Self::from_log(log, ip.clone()))
+            .collect()
+    }
+
+    pub fn from_log(log: Log<'_>, ip: String) -> Result<Self, DecodeError> {
         let vars = log
             .vars()
             .unwrap_or_default()
             .iter()
             .map(|var| RsVar {
                 key: var.key().unwrap_or("").to_string(),
-                val: var.val().unwrap_or("").to_string(),
+                val: RsValue::from_schema(&var),
             })
             .collect();
         let ts: DateTime<FixedOffset> =
-            DateTime::parse_from_rfc3339(log.ts().expect("Log is missing a timestamp"))
-                .expect("Failed to parse timestamp");
+            DateTime::parse_from_rfc3339(log.ts().ok_or(DecodeError::MissingTimestamp)?)
+                .map_err(DecodeError::BadTimestamp)?;
         let context = log.context();
-        Self {
+        let location = log.location().map(|location| RsLocation {
+            file: location.file().unwrap_or("").to_string(),
+            line: location.line(),
+            module: location.module().unwrap_or("").to_string(),
+            crate_name: location.crate_name().unwrap_or("").to_string(),
+        });
+        let trace = log
+            .trace_id()
+            .filter(|trace_id| !trace_id.is_empty())
+            .map(|trace_id| RsTrace {
+                trace_id: trace_id.to_string(),
+                span_id: log.span_id().unwrap_or("").to_string(),
+                parent_span_id: log
+                    .parent_span_id()
+                    .filter(|parent| !parent.is_empty())
+                    .map(str::to_string),
+            });
+        let kind = match log.kind() {
+            Kind::Event => RsKind::Event,
+            Kind::Span => RsKind::Span {
+                duration: Duration::from_micros(log.duration_us()),
+            },
+            Kind(value) => {
+                return Err(DecodeError::OutOfRange {
+                    field: "kind",
+                    value,
+                });
+            }
+        };
+        Ok(Self {
             ts,
+            level: RsLevel::from_schema(log.level())?,
             msg: log.msg().unwrap_or("").to_string(),
             ip,
             context: RsContext {
@@ -78,12 +647,33 @@ impl RsLog {
                     .to_string(),
             },
             vars,
-        }
+            location,
+            trace,
+            kind,
+        })
     }
 
-    pub fn build(self) -> Vec<u8> {
-        let mut builder = flatbuffers::FlatBufferBuilder::with_capacity(1024);
+    pub fn build(&self) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::with_capacity(1024);
+        let log = self.write(&mut builder);
+        builder.finish(log, None);
+        builder.finished_data().to_vec()
+    }
+
+    /// Encodes logs into a single `LogBatch` message
+    pub fn build_batch(logs: &[RsLog]) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::with_capacity(1024);
+        let logs = logs
+            .iter()
+            .map(|log| log.write(&mut builder))
+            .collect::<Vec<_>>();
+        let logs = builder.create_vector(&logs);
+        let batch = LogBatch::create(&mut builder, &LogBatchArgs { logs: Some(logs) });
+        builder.finish(batch, Some(BATCH_IDENTIFIER));
+        builder.finished_data().to_vec()
+    }
 
+    fn write<'a>(&self, builder: &mut FlatBufferBuilder<'a>) -> WIPOffset<Log<'a>> {
         let ts_string =
             builder.create_string(&self.ts.to_rfc3339_opts(chrono::SecondsFormat::Micros, true));
         let msg = builder.create_string(&self.msg);
@@ -92,12 +682,61 @@ impl RsLog {
             .iter()
             .map(|var| {
                 let key_string = builder.create_string(&var.key);
-                let val_string = builder.create_string(&var.val);
+                let val_string = builder.create_string(&var.val.to_string());
+                let (value_type, value) = match &var.val {
+                    RsValue::Int(value) => (
+                        Value::IntValue,
+                        Some(
+                            IntValue::create(builder, &IntValueArgs { value: *value })
+                                .as_union_value(),
+                        ),
+                    ),
+                    RsValue::Float(value) => (
+                        Value::FloatValue,
+                        Some(
+                            FloatValue::create(builder, &FloatValueArgs { value: *value })
+                                .as_union_value(),
+                        ),
+                    ),
+                    RsValue::Bool(value) => (
+                        Value::BoolValue,
+                        Some(
+                            BoolValue::create(builder, &BoolValueArgs { value: *value })
+                                .as_union_value(),
+                        ),
+                    ),
+                    RsValue::Str(_) => (Value::NONE, None),
+                    RsValue::Bytes(bytes) => {
+                        let bytes = builder.create_vector(bytes);
+                        (
+                            Value::BytesValue,
+                            Some(
+                                BytesValue::create(builder, &BytesValueArgs { value: Some(bytes) })
+                                    .as_union_value(),
+                            ),
+                        )
+                    }
+                    RsValue::Timestamp(_) => (
+                        Value::TimestampValue,
+                        Some(
+                            TimestampValue::create(
+                                builder,
+                                &TimestampValueArgs {
+                                    // The text form of a timestamp is RFC 3339 already
+                                    value: Some(val_string),
+                                },
+                            )
+                            .as_union_value(),
+                        ),
+                    ),
+                };
                 Var::create(
-                    &mut builder,
+                    builder,
                     &VarArgs {
                         key: Some(key_string),
                         val: Some(val_string),
+                        value_type,
+                        value,
                     },
                 )
             })
@@ -107,7 +746,7 @@ impl RsLog {
         let os_string = builder.create_string(&self.context.os);
         let version_string = builder.create_string(&self.context.version);
         let context = Context::create(
-            &mut builder,
+            builder,
             &ContextArgs {
                 app: Some(app_string),
                 pid: self.context.pid,
@@ -115,18 +754,53 @@ impl RsLog {
                 version: Some(version_string),
             },
         );
-        let log_offset = Log::create(
-            &mut builder,
+        let location = self.location.as_ref().map(|location| {
+            let file = builder.create_string(&location.file);
+            let module = builder.create_string(&location.module);
+            let crate_name = builder.create_string(&location.crate_name);
+            Location::create(
+                builder,
+                &LocationArgs {
+                    file: Some(file),
+                    line: location.line,
+                    module: Some(module),
+                    crate_name: Some(crate_name),
+                },
+            )
+        });
+        let (trace_id, span_id, parent_span_id) = match &self.trace {
+            Some(trace) => (
+                Some(builder.create_string(&trace.trace_id)),
+                Some(builder.create_string(&trace.span_id)),
+                trace
+                    .parent_span_id
+                    .as_ref()
+                    .map(|parent| builder.create_string(parent)),
+            ),
+            None => (None, None, None),
+        };
+        Log::create(
+            builder,
             &LogArgs {
                 ts: Some(ts_string),
                 msg: Some(msg),
                 context: Some(context),
                 vars: Some(vars_array),
+                level: self.level.to_schema(),
+                location,
+                trace_id,
+                span_id,
+                parent_span_id,
+                kind: match self.kind {
+                    RsKind::Event => Kind::Event,
+                    RsKind::Span { .. } => Kind::Span,
+                },
+                duration_us: self
+                    .kind
+                    .duration()
+                    .map_or(0, |duration| duration.as_micros() as u64),
             },
-        );
-
-        builder.finish(log_offset, None);
-        builder.finished_data().to_vec()
+        )
     }
 }
 
@@ -140,6 +814,156 @@ impl std::fmt::Display for RsLog {
             .collect::<Vec<_>>()
             .join(", ");
 
-        write!(f, "{}: {} {}", formatted_ts, self.msg, vars_str)
+        write!(
+            f,
+            "{} {:>5}: {} {}",
+            formatted_ts,
+            self.level.as_str().to_uppercase(),
+            self.msg,
+            vars_str
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(msg: &str) -> RsLog {
+        let mut log = RsLog::new(
+            DateTime::parse_from_rfc3339("2024-05-01T12:00:00.000001+02:00").unwrap(),
+            RsLevel::Warn,
+            msg.to_string(),
+            RsContext {
+                app: "app".to_string(),
+                pid: 42,
+                os: "linux".to_string(),
+                version: "1.0".to_string(),
+            },
+            vec![
+                ("n".to_string(), RsValue::Int(-3)),
+                ("f".to_string(), RsValue::Float(1.5)),
+                ("ok".to_string(), RsValue::Bool(true)),
+                ("s".to_string(), RsValue::Str("text".to_string())),
+                ("b".to_string(), RsValue::Bytes(vec![0, 255])),
+            ],
+            None,
+        );
+        log.trace = Some(RsTrace {
+            trace_id: "t".to_string(),
+            span_id: "s".to_string(),
+            parent_span_id: None,
+        });
+        log.kind = RsKind::Span {
+            duration: Duration::from_micros(7),
+        };
+        log
+    }
+
+    fn build_with(level: Level, kind: Kind) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let ts = builder.create_string("2024-05-01T12:00:00Z");
+        let log = Log::create(
+            &mut builder,
+            &LogArgs {
+                ts: Some(ts),
+                level,
+                kind,
+                ..LogArgs::default()
+            },
+        );
+        builder.finish(log, None);
+        builder.finished_data().to_vec()
+    }
+
+    #[test]
+    fn valid_batch_is_decoded() {
+        let buf = RsLog::build_batch(&[sample("one"), sample("two")]);
+        let logs = RsLog::decode_message(&buf, "10.0.0.1".to_string()).unwrap();
+
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].msg, "one");
+        assert_eq!(logs[1].msg, "two");
+        let log = &logs[0];
+        assert_eq!(log.ts, sample("one").ts);
+        assert_eq!(log.level, RsLevel::Warn);
+        assert_eq!(log.ip, "10.0.0.1");
+        assert_eq!(log.context.pid, 42);
+        assert_eq!(log.trace, sample("one").trace);
+        assert_eq!(log.kind.duration(), Some(Duration::from_micros(7)));
+        let vars: Vec<_> = log.vars.iter().map(|var| (&*var.key, &var.val)).collect();
+        assert_eq!(
+            vars,
+            [
+                ("n", &RsValue::Int(-3)),
+                ("f", &RsValue::Float(1.5)),
+                ("ok", &RsValue::Bool(true)),
+                ("s", &RsValue::Str("text".to_string())),
+                ("b", &RsValue::Bytes(vec![0, 255])),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_buffer_is_rejected() {
+        let single = sample("one").build();
+        let batch = RsLog::build_batch(&[sample("one"), sample("two")]);
+        for buf in [&single, &batch] {
+            let result = RsLog::decode_message(&buf[..buf.len() / 2], String::new());
+            assert!(matches!(result, Err(DecodeError::Invalid(_))), "{result:?}");
+        }
+        assert!(RsLog::decode_message(&[], String::new()).is_err());
+    }
+
+    #[test]
+    fn wrong_batch_identifier_is_rejected() {
+        let mut buf = RsLog::build_batch(&[sample("one")]);
+        buf[4..8].copy_from_slice(b"HBAX");
+        assert!(RsLog::decode_message(&buf, String::new()).is_err());
+    }
+
+    #[test]
+    fn oversized_message_is_rejected() {
+        let mut buf = vec![0; MAX_MESSAGE_SIZE + 1];
+        let result = RsLog::decode_message(&buf, String::new());
+        assert!(
+            matches!(result, Err(DecodeError::TooLarge { .. })),
+            "{result:?}"
+        );
+
+        buf[4..8].copy_from_slice(BATCH_IDENTIFIER.as_bytes());
+        let result = RsLog::decode_message(&buf, String::new());
+        assert!(
+            matches!(result, Err(DecodeError::TooLarge { .. })),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn out_of_range_enum_is_rejected() {
+        assert!(RsLog::decode(&build_with(Level::Fatal, Kind::Span), String::new()).is_ok());
+
+        let result = RsLog::decode(&build_with(Level(42), Kind::Event), String::new());
+        assert!(
+            matches!(
+                result,
+                Err(DecodeError::OutOfRange {
+                    field: "level",
+                    value: 42
+                })
+            ),
+            "{result:?}"
+        );
+        let result = RsLog::decode(&build_with(Level::Info, Kind(-1)), String::new());
+        assert!(
+            matches!(
+                result,
+                Err(DecodeError::OutOfRange {
+                    field: "kind",
+                    value: -1
+                })
+            ),
+            "{result:?}"
+        );
     }
 }
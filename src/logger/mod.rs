@@ -0,0 +1,368 @@
+mod queue;
+mod spool;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, FixedOffset, Local};
+use nng::{
+    Socket,
+    options::{Options, ReconnectMaxTime, ReconnectMinTime, SendTimeout},
+};
+use std::{
+    path::PathBuf,
+    sync::{Arc, OnceLock, atomic::Ordering},
+    time::Duration,
+};
+
+use crate::prelude::{RsContext, RsLevel, RsLocation, RsLog, RsValue};
+use crate::span;
+use queue::{Batching, Counters, LogQueue};
+use spool::{Outbox, Reconnect, Spool};
+
+pub use queue::{LoggerStats, Overflow};
+
+pub mod prelude {
+    pub use super::{
+        GLOBAL_LOGGER, Logger, LoggerBuilder, LoggerGuard, LoggerStats, Overflow,
+        current_timestamp, global_log, global_logger, global_send,
+    };
+}
+
+/// Queue capacity of loggers that batch without setting one
+pub const DEFAULT_QUEUE_CAPACITY: usize = 10_000;
+
+/// Waits between connection attempts unless `with_reconnect` sets others
+const DEFAULT_RECONNECT: Reconnect = Reconnect {
+    min: Duration::from_millis(100),
+    max: Duration::from_secs(30),
+};
+
+/// How long a send waits for the server unless `with_send_timeout` sets another time
+const DEFAULT_SEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long `LoggerGuard` waits for queued logs to be sent when dropped
+pub const DEFAULT_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The logger used by the logging macros. It is shared without a lock, sending is thread safe.
+pub static GLOBAL_LOGGER: OnceLock<Logger> = OnceLock::new();
+
+pub struct LoggerBuilder {
+    bind: Option<String>,
+    app_name: String,
+    version: String,
+    queue_capacity: Option<usize>,
+    overflow: Overflow,
+    batching: Option<Batching>,
+    reconnect: Reconnect,
+    send_timeout: Duration,
+    spool: Option<(PathBuf, u64)>,
+}
+
+impl Default for LoggerBuilder {
+    fn default() -> Self {
+        Self {
+            bind: None,
+            app_name: "default".to_string(),
+            version: "0.0.0".to_string(),
+            queue_capacity: None,
+            overflow: Overflow::default(),
+            batching: None,
+            reconnect: DEFAULT_RECONNECT,
+            send_timeout: DEFAULT_SEND_TIMEOUT,
+            spool: None,
+        }
+    }
+}
+
+impl LoggerBuilder {
+    pub fn with_bind(mut self, bind: String) -> Self {
+        self.bind = Some(bind);
+        self
+    }
+
+    pub fn with_address_port(mut self, address: &str, port: u16) -> Self {
+        self.bind = Some(format!("tcp://{address}:{port}"));
+        self
+    }
+
+    pub fn with_app_name(mut self, app_name: &str) -> Self {
+        self.app_name = app_name.to_string();
+        self
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    /// Queues logs for a background thread to send instead of sending them on the logging
+    /// thread. At most `capacity` logs wait in the queue, see `with_overflow` for the rest.
+    pub fn with_async(mut self, capacity: usize) -> Self {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// What an async logger does when its queue is full, `Overflow::DropNewest` by default
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Sends logs in batches of up to `max_logs`, waiting at most `max_delay` for a batch to
+    /// fill. Batches are sent by the background thread, so this makes the logger async with a
+    /// queue of `DEFAULT_QUEUE_CAPACITY` unless `with_async` sets another capacity.
+    pub fn with_batching(mut self, max_logs: usize, max_delay: Duration) -> Self {
+        self.batching = Some(Batching {
+            max_logs,
+            max_delay,
+        });
+        self
+    }
+
+    /// How long to wait between attempts to reach the server, starting at `min` and doubling
+    /// up to `max` while it can't be reached. 100ms and 30s by default.
+    pub fn with_reconnect(mut self, min: Duration, max: Duration) -> Self {
+        self.reconnect = Reconnect { min, max };
+        self
+    }
+
+    /// How long sending a log waits for the server before it counts as failed, or is spooled
+    /// with `with_spool`. Direct loggers wait on the logging thread. 1s by default.
+    pub fn with_send_timeout(mut self, timeout: Duration) -> Self {
+        self.send_timeout = timeout;
+        self
+    }
+
+    /// Keeps logs that can't be sent in a file of at most `max_bytes` until the server can be
+    /// reached, then sends them in order. Logs still in the file when the program exits are
+    /// sent by the next logger using it. Logs that don't fit are dropped. Spooling is done by
+    /// the background thread, so this makes the logger async like `with_batching`.
+    pub fn with_spool(mut self, path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        self.spool = Some((path.into(), max_bytes));
+        self
+    }
+
+    /// Installs the logger as the global logger. Keep the returned guard until the program is
+    /// done logging, dropping it flushes the logger.
+    pub fn build(self) -> Result<LoggerGuard> {
+        let logger = self.build_local()?;
+        GLOBAL_LOGGER
+            .set(logger)
+            .map_err(|_| anyhow!("Global logger is already set"))?;
+
+        Ok(LoggerGuard {
+            timeout: DEFAULT_FLUSH_TIMEOUT,
+            finished: false,
+        })
+    }
+
+    /// Builds a logger that is not installed as the global logger, with its own server
+    /// connection and context. Clones share both. When the last clone of an async logger is
+    /// dropped it keeps sending what is left in its queue for up to 5s, then drops the rest and
+    /// reports it on stderr. Use `flush` to wait for the queue before that.
+    pub fn build_local(self) -> Result<Logger> {
+        let bind = self
+            .bind
+            .ok_or_else(|| anyhow!("Bind address must be specified"))
+            .context("Building Logger")?;
+        let socket = Socket::new(nng::Protocol::Push0).context("Failed to create a new socket")?;
+        if self.reconnect.min.is_zero() || self.reconnect.min > self.reconnect.max {
+            bail!("Reconnect times must be positive with min at most max");
+        }
+        socket
+            .set_opt::<ReconnectMinTime>(Some(self.reconnect.min))
+            .context("Failed to set the reconnect time")?;
+        socket
+            .set_opt::<ReconnectMaxTime>(Some(self.reconnect.max))
+            .context("Failed to set the reconnect time")?;
+        // A push socket without a server waits forever on send otherwise
+        socket
+            .set_opt::<SendTimeout>(Some(self.send_timeout))
+            .context("Failed to set the send timeout")?;
+        // Connects in the background, so a server that is down is retried instead of failing
+        socket
+            .dial_async(&bind)
+            .context("Failed to connect to the server")?;
+
+        let pid = std::process::id();
+        let os = Self::get_os();
+        let context = RsContext {
+            app: self.app_name,
+            pid,
+            os,
+            version: self.version,
+        };
+
+        let counters = Arc::new(Counters::default());
+        if self.batching.is_some_and(|batching| batching.max_logs == 0) {
+            bail!("Batches must hold at least 1 log");
+        }
+        let queue_capacity = match self.queue_capacity {
+            None if self.batching.is_some() || self.spool.is_some() => Some(DEFAULT_QUEUE_CAPACITY),
+            capacity => capacity,
+        };
+        let spool = match self.spool {
+            Some((path, max_bytes)) => Some(Spool::open(path, max_bytes)?),
+            None => None,
+        };
+        let transport = match queue_capacity {
+            Some(0) => bail!("Queue capacity must be at least 1"),
+            Some(capacity) => Transport::Queued(LogQueue::spawn(
+                Outbox::new(socket, spool, self.reconnect, counters.clone()),
+                capacity,
+                self.overflow,
+                self.batching,
+                counters.clone(),
+            )?),
+            None => Transport::Direct(socket),
+        };
+
+        Ok(Logger {
+            _bind: bind,
+            context,
+            transport: Arc::new(transport),
+            counters,
+        })
+    }
+
+    fn get_os() -> String {
+        let kind = sys_info::os_type().unwrap_or_else(|_| "Unknown".to_string());
+        let release = sys_info::os_release().unwrap_or_else(|_| "Unknown".to_string());
+        format!("{kind} {release}")
+    }
+}
+
+enum Transport {
+    /// Logs are sent on the thread that made them
+    Direct(Socket),
+    Queued(LogQueue),
+}
+
+#[derive(Clone)]
+pub struct Logger {
+    _bind: String,
+    context: RsContext,
+    transport: Arc<Transport>,
+    counters: Arc<Counters>,
+}
+
+impl Logger {
+    pub fn builder() -> LoggerBuilder {
+        LoggerBuilder::default()
+    }
+
+    pub fn log(
+        &self,
+        ts: DateTime<FixedOffset>,
+        level: RsLevel,
+        msg: impl Into<String>,
+        vars: Vec<(String, RsValue)>,
+        location: Option<RsLocation>,
+    ) -> Result<()> {
+        let mut log = RsLog::new(ts, level, msg.into(), self.context.clone(), vars, location);
+        log.trace = span::current();
+        self.send(log)
+    }
+
+    /// Sends a log as it is, except that the context is always this logger's. Async loggers
+    /// only queue it, failures to send show up in `stats`.
+    pub fn send(&self, mut log: RsLog) -> Result<()> {
+        log.context = self.context.clone();
+        match self.transport.as_ref() {
+            Transport::Direct(socket) => {
+                if let Err(e) = socket.send(log.build()) {
+                    self.counters.failed.fetch_add(1, Ordering::Relaxed);
+                    bail!("Failed to send message: {:?}", e);
+                }
+                self.counters.sent.fetch_add(1, Ordering::Relaxed);
+            }
+            Transport::Queued(queue) => queue.push(log),
+        }
+        Ok(())
+    }
+
+    /// Waits at most `timeout` for the logs sent before the call to be handed to the socket or
+    /// spooled. Logs that are still pending show in the returned stats.
+    pub fn flush(&self, timeout: Duration) -> LoggerStats {
+        if let Transport::Queued(queue) = self.transport.as_ref() {
+            queue.flush(timeout);
+        }
+        self.stats()
+    }
+
+    pub fn stats(&self) -> LoggerStats {
+        let queued = match self.transport.as_ref() {
+            Transport::Direct(_) => 0,
+            Transport::Queued(queue) => queue.len(),
+        };
+        self.counters.stats(queued)
+    }
+}
+
+/// Flushes the global logger when dropped, printing what could not be sent to stderr
+#[must_use = "dropping the guard flushes the logger right away"]
+pub struct LoggerGuard {
+    timeout: Duration,
+    finished: bool,
+}
+
+impl LoggerGuard {
+    /// How long to wait for queued logs when the guard is dropped, `DEFAULT_FLUSH_TIMEOUT` by
+    /// default
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Flushes the logger and returns what it did with its logs instead of printing it
+    pub fn finish(mut self) -> LoggerStats {
+        self.finished = true;
+        self.flush()
+    }
+
+    fn flush(&self) -> LoggerStats {
+        GLOBAL_LOGGER
+            .get()
+            .map(|logger| logger.flush(self.timeout))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for LoggerGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let stats = self.flush();
+        if stats.dropped + stats.failed + stats.pending() > 0 {
+            eprintln!("heimdall: not every log was sent: {stats}");
+        }
+    }
+}
+
+pub fn global_logger() -> Result<&'static Logger> {
+    GLOBAL_LOGGER
+        .get()
+        .ok_or_else(|| anyhow!("Global logger is not initialized"))
+}
+
+pub fn global_log(
+    ts: DateTime<FixedOffset>,
+    level: RsLevel,
+    msg: impl Into<String>,
+    vars: Vec<(String, RsValue)>,
+    location: Option<RsLocation>,
+) -> Result<()> {
+    global_logger()?
+        .log(ts, level, msg, vars, location)
+        .context("Logging message using global logger")
+}
+
+pub fn global_send(log: RsLog) -> Result<()> {
+    global_logger()?
+        .send(log)
+        .context("Sending log using global logger")
+}
+
+pub fn current_timestamp() -> DateTime<FixedOffset> {
+    Local::now().into()
+}
@@ -0,0 +1,532 @@
+use anyhow::{Context, Result};
+use crossbeam_queue::ArrayQueue;
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::spool::Outbox;
+use crate::{log::MAX_MESSAGE_SIZE, prelude::RsLog};
+
+/// How long the sender sleeps when there is nothing to send, pushes wake it up earlier
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// How long a dropped logger keeps sending what is left in its queue before dropping it
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What an async logger does with a log when its queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// The new log is dropped
+    #[default]
+    DropNewest,
+    /// The oldest queued log is dropped to make room for the new one
+    DropOldest,
+    /// The logging thread waits until there is room
+    Block,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "drop-newest" => Ok(Overflow::DropNewest),
+            "drop-oldest" => Ok(Overflow::DropOldest),
+            "block" => Ok(Overflow::Block),
+            _ => Err(format!(
+                "Unknown overflow policy: {s}, expected drop-newest, drop-oldest or block"
+            )),
+        }
+    }
+}
+
+/// When queued logs are sent together in one message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Batching {
+    /// A batch is sent as soon as it holds this many logs
+    pub max_logs: usize,
+    /// Or once its first log has waited this long
+    pub max_delay: Duration,
+}
+
+/// What a logger did with its logs since it was built
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoggerStats {
+    /// Logs handed to the socket
+    pub sent: u64,
+    /// Logs dropped because the queue was full
+    pub dropped: u64,
+    /// Logs the socket refused
+    pub failed: u64,
+    /// Logs waiting in the queue or in a batch that was not sent yet
+    pub queued: usize,
+    /// Logs kept in the spool until the server can be reached
+    pub spooled: u64,
+}
+
+impl LoggerStats {
+    /// Logs that were neither sent nor dropped yet
+    pub fn pending(&self) -> u64 {
+        self.queued as u64 + self.spooled
+    }
+}
+
+impl fmt::Display for LoggerStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} sent, {} dropped, {} failed, {} pending",
+            self.sent,
+            self.dropped,
+            self.failed,
+            self.pending()
+        )
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Counters {
+    pub sent: AtomicU64,
+    pub dropped: AtomicU64,
+    pub failed: AtomicU64,
+    pub spooled: AtomicU64,
+}
+
+impl Counters {
+    pub fn stats(&self, queued: usize) -> LoggerStats {
+        LoggerStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            queued,
+            spooled: self.spooled.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Batches too large for the server are split in halves until they fit
+fn send_batch(outbox: &mut Outbox, mut logs: Vec<RsLog>) {
+    if logs.len() <= 1 {
+        if let Some(log) = logs.pop() {
+            outbox.send(log.build(), 1);
+        }
+        return;
+    }
+
+    let buf = RsLog::build_batch(&logs);
+    if buf.len() > MAX_MESSAGE_SIZE {
+        let second = logs.split_off(logs.len() / 2);
+        send_batch(outbox, logs);
+        send_batch(outbox, second);
+        return;
+    }
+    outbox.send(buf, logs.len() as u64);
+}
+
+struct Shared {
+    queue: ArrayQueue<RsLog>,
+    overflow: Overflow,
+    batching: Option<Batching>,
+    counters: Arc<Counters>,
+    closed: AtomicBool,
+    /// Wakes loggers blocked on a full queue
+    room: (Mutex<()>, Condvar),
+    /// Logs taken from the queue into a batch that was not sent yet
+    batched: AtomicUsize,
+    /// Callers waiting in `flush`, the sender doesn't wait for batches to fill while there are any
+    flushing: AtomicUsize,
+    /// Rounds of the sender, one round sends everything that was queued when it started
+    rounds_started: AtomicU64,
+    /// Last round that was finished, `flush` waits on it
+    rounds_finished: (Mutex<u64>, Condvar),
+}
+
+/// Bounded lock-free queue of logs, drained by a background thread that owns the socket and
+/// the spool
+pub(super) struct LogQueue {
+    shared: Arc<Shared>,
+    /// Returns how many logs it dropped at shutdown
+    sender: Option<JoinHandle<u64>>,
+}
+
+impl LogQueue {
+    pub fn spawn(
+        outbox: Outbox,
+        capacity: usize,
+        overflow: Overflow,
+        batching: Option<Batching>,
+        counters: Arc<Counters>,
+    ) -> Result<Self> {
+        let shared = Arc::new(Shared {
+            queue: ArrayQueue::new(capacity),
+            overflow,
+            batching,
+            counters,
+            closed: AtomicBool::new(false),
+            room: (Mutex::new(()), Condvar::new()),
+            batched: AtomicUsize::new(0),
+            flushing: AtomicUsize::new(0),
+            rounds_started: AtomicU64::new(0),
+            rounds_finished: (Mutex::new(0), Condvar::new()),
+        });
+        let sender = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name("heimdall-sender".to_string())
+                .spawn(move || send_queued(&shared, outbox))
+                .context("Failed to start the sender thread")?
+        };
+
+        Ok(Self {
+            shared,
+            sender: Some(sender),
+        })
+    }
+
+    pub fn push(&self, log: RsLog) {
+        let shared = &self.shared;
+        match shared.overflow {
+            Overflow::DropNewest => {
+                if shared.queue.push(log).is_err() {
+                    shared.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Overflow::DropOldest => {
+                if shared.queue.force_push(log).is_some() {
+                    shared.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Overflow::Block => {
+                let (lock, condvar) = &shared.room;
+                let mut log = log;
+                while let Err(rejected) = shared.queue.push(log) {
+                    self.wake_sender();
+                    // Tried again under the lock, the sender holds it to signal that it made room
+                    let guard = lock.lock().unwrap();
+                    match shared.queue.push(rejected) {
+                        Ok(()) => break,
+                        Err(rejected) => {
+                            log = rejected;
+                            let _ = condvar.wait_timeout(guard, IDLE_WAIT);
+                        }
+                    }
+                }
+            }
+        }
+        self.wake_sender();
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.queue.len() + self.shared.batched.load(Ordering::Relaxed)
+    }
+
+    /// Waits at most `timeout` for the sender to send or spool everything queued before the
+    /// call, returns whether it did
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let shared = &self.shared;
+        shared.flushing.fetch_add(1, Ordering::SeqCst);
+        // The first round started after this point sees the logs queued before the call
+        let round = shared.rounds_started.load(Ordering::SeqCst) + 1;
+        self.wake_sender();
+
+        let (lock, condvar) = &shared.rounds_finished;
+        let finished = lock.lock().unwrap();
+        let (_finished, result) = condvar
+            .wait_timeout_while(finished, timeout, |finished| *finished < round)
+            .unwrap();
+        shared.flushing.fetch_sub(1, Ordering::SeqCst);
+        !result.timed_out()
+    }
+
+    fn wake_sender(&self) {
+        if let Some(sender) = &self.sender {
+            sender.thread().unpark();
+        }
+    }
+}
+
+/// Sends what is left in the queue for at most `SHUTDOWN_TIMEOUT` before returning. What
+/// can't be sent stays in the spool, without one it is dropped and reported on stderr.
+impl Drop for LogQueue {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.wake_sender();
+        if let Some(sender) = self.sender.take()
+            && let Ok(dropped) = sender.join()
+            && dropped > 0
+        {
+            eprintln!("heimdall: {dropped} logs were dropped, they could not be sent in time");
+        }
+    }
+}
+
+/// Returns how many logs were dropped because the shutdown took too long
+fn send_queued(shared: &Shared, mut outbox: Outbox) -> u64 {
+    let mut batch = Vec::new();
+    let mut batch_started = Instant::now();
+    let mut deadline = None;
+    loop {
+        let round = shared.rounds_started.fetch_add(1, Ordering::SeqCst) + 1;
+        let flushing = shared.flushing.load(Ordering::SeqCst) > 0;
+        if deadline.is_none() && shared.closed.load(Ordering::Acquire) {
+            deadline = Some(Instant::now() + SHUTDOWN_TIMEOUT);
+        }
+        let past_deadline = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+        while let Some(log) = shared.queue.pop() {
+            if past_deadline() {
+                return give_up(shared, batch.len() + 1);
+            }
+            if shared.overflow == Overflow::Block {
+                let _guard = shared.room.0.lock().unwrap();
+                shared.room.1.notify_all();
+            }
+            let Some(batching) = shared.batching else {
+                outbox.send(log.build(), 1);
+                continue;
+            };
+            if batch.is_empty() {
+                batch_started = Instant::now();
+            }
+            batch.push(log);
+            if batch.len() >= batching.max_logs {
+                send_batch(&mut outbox, std::mem::take(&mut batch));
+            }
+            shared.batched.store(batch.len(), Ordering::Relaxed);
+        }
+
+        let closed = shared.closed.load(Ordering::Acquire) && shared.queue.is_empty();
+        let wait = match shared.batching {
+            Some(batching) if !batch.is_empty() => {
+                let waited = batch_started.elapsed();
+                if closed && past_deadline() {
+                    return give_up(shared, batch.len());
+                } else if closed || flushing || waited >= batching.max_delay {
+                    send_batch(&mut outbox, std::mem::take(&mut batch));
+                    shared.batched.store(0, Ordering::Relaxed);
+                    IDLE_WAIT
+                } else {
+                    batching.max_delay - waited
+                }
+            }
+            _ => IDLE_WAIT,
+        };
+
+        if outbox
+            .retry_at()
+            .is_some_and(|retry_at| (closed && !past_deadline()) || retry_at <= Instant::now())
+        {
+            outbox.replay();
+        }
+        let wait = match outbox.retry_at() {
+            Some(retry_at) => wait.min(retry_at.saturating_duration_since(Instant::now())),
+            None => wait,
+        };
+
+        {
+            let (lock, condvar) = &shared.rounds_finished;
+            *lock.lock().unwrap() = round;
+            condvar.notify_all();
+        }
+        if closed {
+            return 0;
+        }
+        thread::park_timeout(wait);
+    }
+}
+
+/// Drops the logs left in the queue and `batched` more that were taken from it
+fn give_up(shared: &Shared, batched: usize) -> u64 {
+    let mut dropped = batched as u64;
+    while shared.queue.pop().is_some() {
+        dropped += 1;
+    }
+    shared.batched.store(0, Ordering::Relaxed);
+    shared
+        .counters
+        .dropped
+        .fetch_add(dropped, Ordering::Relaxed);
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::spool::Reconnect;
+    use crate::prelude::{RsContext, RsLevel};
+    use nng::{
+        Protocol, Socket,
+        options::{Options, RecvMaxSize, RecvTimeout, SendTimeout},
+    };
+
+    const SEND_TIMEOUT: Duration = Duration::from_millis(500);
+
+    fn log(msg: impl Into<String>) -> RsLog {
+        RsLog::new(
+            crate::logger::current_timestamp(),
+            RsLevel::Info,
+            msg.into(),
+            RsContext::default(),
+            Vec::new(),
+            None,
+        )
+    }
+
+    /// An outbox sending to `inproc://heimdall-queue-{name}`, along with the socket receiving
+    /// from it if `listen` is set. Without one sends time out after `SEND_TIMEOUT`.
+    fn outbox(name: &str, listen: bool) -> (Outbox, Option<Socket>, Arc<Counters>) {
+        let url = format!("inproc://heimdall-queue-{name}");
+        let receiver = listen.then(|| {
+            let receiver = Socket::new(Protocol::Pull0).unwrap();
+            receiver
+                .set_opt::<RecvTimeout>(Some(Duration::from_millis(200)))
+                .unwrap();
+            receiver
+                .set_opt::<RecvMaxSize>(2 * MAX_MESSAGE_SIZE)
+                .unwrap();
+            receiver.listen(&url).unwrap();
+            receiver
+        });
+        let socket = Socket::new(Protocol::Push0).unwrap();
+        socket.set_opt::<SendTimeout>(Some(SEND_TIMEOUT)).unwrap();
+        socket.dial_async(&url).unwrap();
+
+        let counters = Arc::new(Counters::default());
+        let reconnect = Reconnect {
+            min: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+        };
+        let outbox = Outbox::new(socket, None, reconnect, counters.clone());
+        (outbox, receiver, counters)
+    }
+
+    /// Every message received until none arrives for a while, as its decoded logs
+    fn received(receiver: &Socket) -> Vec<(usize, Vec<String>)> {
+        let mut messages = Vec::new();
+        while let Ok(message) = receiver.recv() {
+            let logs = RsLog::decode_message(&message, String::new()).unwrap();
+            messages.push((message.len(), logs.into_iter().map(|log| log.msg).collect()));
+        }
+        messages
+    }
+
+    /// Queue of `capacity` whose sender is stuck sending a first log for `SEND_TIMEOUT`, so
+    /// that whatever is pushed next stays queued
+    fn stalled_queue(name: &str, capacity: usize, overflow: Overflow) -> (LogQueue, Arc<Counters>) {
+        let (outbox, _, counters) = outbox(name, false);
+        let queue = LogQueue::spawn(outbox, capacity, overflow, None, counters.clone()).unwrap();
+        queue.push(log("first"));
+        let started = Instant::now();
+        while !queue.shared.queue.is_empty() {
+            assert!(
+                started.elapsed() < SEND_TIMEOUT,
+                "the sender never took the log"
+            );
+            thread::yield_now();
+        }
+        (queue, counters)
+    }
+
+    fn queued(queue: &LogQueue) -> Vec<String> {
+        std::iter::from_fn(|| queue.shared.queue.pop())
+            .map(|log| log.msg)
+            .collect()
+    }
+
+    #[test]
+    fn flush_waits_for_queued_logs() {
+        let (outbox, receiver, counters) = outbox("flush", true);
+        // Batches would otherwise wait an hour to fill up
+        let batching = Batching {
+            max_logs: 1000,
+            max_delay: Duration::from_secs(3600),
+        };
+        let queue = LogQueue::spawn(
+            outbox,
+            100,
+            Overflow::Block,
+            Some(batching),
+            counters.clone(),
+        )
+        .unwrap();
+        for i in 0..10 {
+            queue.push(log(i.to_string()));
+        }
+
+        assert!(queue.flush(Duration::from_secs(5)));
+        assert_eq!(counters.stats(queue.len()).sent, 10);
+        assert_eq!(queue.len(), 0);
+        let messages = received(&receiver.unwrap());
+        let logs: Vec<String> = messages.into_iter().flat_map(|(_, logs)| logs).collect();
+        assert_eq!(logs, (0..10).map(|i| i.to_string()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn full_queue_drops_the_newest_log() {
+        let (queue, counters) = stalled_queue("drop-newest", 2, Overflow::DropNewest);
+        for msg in ["a", "b", "c"] {
+            queue.push(log(msg));
+        }
+
+        assert_eq!(counters.stats(0).dropped, 1);
+        assert_eq!(queued(&queue), ["a", "b"]);
+    }
+
+    #[test]
+    fn full_queue_drops_the_oldest_log() {
+        let (queue, counters) = stalled_queue("drop-oldest", 2, Overflow::DropOldest);
+        for msg in ["a", "b", "c"] {
+            queue.push(log(msg));
+        }
+
+        assert_eq!(counters.stats(0).dropped, 1);
+        assert_eq!(queued(&queue), ["b", "c"]);
+    }
+
+    #[test]
+    fn full_queue_blocks_until_there_is_room() {
+        let (queue, counters) = stalled_queue("block", 2, Overflow::Block);
+        queue.push(log("a"));
+        queue.push(log("b"));
+
+        thread::scope(|scope| {
+            let pusher = scope.spawn(|| queue.push(log("c")));
+            thread::sleep(Duration::from_millis(50));
+            assert!(!pusher.is_finished());
+
+            assert_eq!(queue.shared.queue.pop().unwrap().msg, "a");
+            pusher.join().unwrap();
+        });
+
+        assert_eq!(counters.stats(0).dropped, 0);
+        assert_eq!(queued(&queue), ["b", "c"]);
+    }
+
+    #[test]
+    fn oversized_batch_is_split() {
+        let (mut outbox, receiver, counters) = outbox("split", true);
+        let logs: Vec<RsLog> = (0..8)
+            .map(|i| log(format!("{i}{}", "x".repeat(MAX_MESSAGE_SIZE / 5))))
+            .collect();
+        assert!(RsLog::build_batch(&logs).len() > MAX_MESSAGE_SIZE);
+
+        send_batch(&mut outbox, logs.clone());
+
+        let messages = received(&receiver.unwrap());
+        assert!(messages.len() > 1);
+        for (size, _) in &messages {
+            assert!(*size <= MAX_MESSAGE_SIZE, "{size}");
+        }
+        let received: Vec<String> = messages.into_iter().flat_map(|(_, logs)| logs).collect();
+        let sent: Vec<String> = logs.into_iter().map(|log| log.msg).collect();
+        assert_eq!(received, sent);
+        assert_eq!(counters.stats(0).sent, 8);
+    }
+}
@@ -0,0 +1,430 @@
+use anyhow::{Context, Result};
+use nng::Socket;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use super::queue::Counters;
+
+/// Bytes at the start of the file, holding where the first message that was not sent starts
+const OFFSET_SIZE: u64 = 8;
+
+/// Bytes before every spooled message: its length and how many logs it holds
+const HEADER_SIZE: u64 = 8;
+
+/// Messages that could not be sent, kept on disk in the order they were made so that they
+/// survive restarts. The file is emptied once everything in it was sent, and sent messages are
+/// cut off the front when a new one wouldn't fit.
+pub(super) struct Spool {
+    file: File,
+    path: PathBuf,
+    /// Bound of the file size, offset included
+    max_bytes: u64,
+    /// Size of the file, sent messages included
+    len: u64,
+    /// Start of the first message that was not sent yet, also stored at the start of the file
+    sent_up_to: u64,
+    /// Logs in the messages that were not sent yet
+    logs: u64,
+}
+
+impl Spool {
+    /// Opens the spool at `path`, keeping what a previous run could not send
+    pub fn open(path: PathBuf, max_bytes: u64) -> Result<Self> {
+        let file = Self::open_file(&path)
+            .with_context(|| format!("Failed to open the spool file {}", path.display()))?;
+        let mut spool = Self {
+            file,
+            path,
+            max_bytes,
+            len: OFFSET_SIZE,
+            sent_up_to: OFFSET_SIZE,
+            logs: 0,
+        };
+        spool
+            .recover()
+            .with_context(|| format!("Failed to read the spool file {}", spool.path.display()))?;
+        Ok(spool)
+    }
+
+    fn open_file(path: &PathBuf) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+    }
+
+    /// Counts the logs that were not sent and cuts off a message that was only partly written
+    fn recover(&mut self) -> io::Result<()> {
+        let size = self.file.metadata()?.len();
+        if size < OFFSET_SIZE {
+            return self.clear();
+        }
+
+        let mut offset = [0; OFFSET_SIZE as usize];
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_exact(&mut offset)?;
+        let offset = u64::from_le_bytes(offset);
+        // Only a file that was not written by a spool can hold another offset
+        self.sent_up_to = if (OFFSET_SIZE..=size).contains(&offset) {
+            offset
+        } else {
+            OFFSET_SIZE
+        };
+
+        self.len = self.sent_up_to;
+        while self.len < size {
+            match self.read_header(self.len)? {
+                Some((length, logs)) if self.len + HEADER_SIZE + length <= size => {
+                    self.len += HEADER_SIZE + length;
+                    self.logs += logs;
+                }
+                _ => break,
+            }
+        }
+        if self.logs == 0 {
+            self.clear()
+        } else if self.len < size {
+            self.file.set_len(self.len)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.logs == 0
+    }
+
+    /// Logs waiting to be sent
+    pub fn logs(&self) -> u64 {
+        self.logs
+    }
+
+    /// Appends a message, returns false if it doesn't fit
+    pub fn push(&mut self, message: &[u8], logs: u64) -> io::Result<bool> {
+        let size = HEADER_SIZE + message.len() as u64;
+        if self.len + size > self.max_bytes && self.sent_up_to > OFFSET_SIZE {
+            self.compact()?;
+        }
+        if self.len + size > self.max_bytes {
+            return Ok(false);
+        }
+
+        let mut frame = Vec::with_capacity(size as usize);
+        frame.extend_from_slice(&(message.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&(logs as u32).to_le_bytes());
+        frame.extend_from_slice(message);
+        self.file.seek(SeekFrom::Start(self.len))?;
+        self.file.write_all(&frame)?;
+        self.len += size;
+        self.logs += logs;
+        Ok(true)
+    }
+
+    /// Sends the spooled messages in order until `send` fails, returns whether all were sent.
+    /// The position is stored after every message, so a restart only sends again the message
+    /// that was being sent.
+    pub fn replay(&mut self, mut send: impl FnMut(Vec<u8>) -> bool) -> io::Result<bool> {
+        while self.sent_up_to < self.len {
+            let Some((length, logs)) = self.read_header(self.sent_up_to)? else {
+                break;
+            };
+            let mut message = vec![0; length as usize];
+            self.file.read_exact(&mut message)?;
+            if !send(message) {
+                return Ok(false);
+            }
+            self.sent_up_to += HEADER_SIZE + length;
+            self.logs -= logs;
+            self.write_offset()?;
+        }
+
+        self.clear()?;
+        Ok(true)
+    }
+
+    /// Empties the file
+    fn clear(&mut self) -> io::Result<()> {
+        self.file.set_len(OFFSET_SIZE)?;
+        self.len = OFFSET_SIZE;
+        self.sent_up_to = OFFSET_SIZE;
+        self.logs = 0;
+        self.write_offset()
+    }
+
+    fn write_offset(&mut self) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&self.sent_up_to.to_le_bytes())
+    }
+
+    /// Cuts the sent messages off the front of the file. The rest is written to a new file that
+    /// replaces the spool, so a crash leaves either the old or the new file.
+    fn compact(&mut self) -> io::Result<()> {
+        let mut unsent = Vec::with_capacity((self.len - self.sent_up_to) as usize);
+        self.file.seek(SeekFrom::Start(self.sent_up_to))?;
+        (&mut self.file)
+            .take(self.len - self.sent_up_to)
+            .read_to_end(&mut unsent)?;
+
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let mut file = File::create(&temp)?;
+        file.write_all(&OFFSET_SIZE.to_le_bytes())?;
+        file.write_all(&unsent)?;
+        file.sync_all()?;
+        fs::rename(&temp, &self.path)?;
+
+        self.file = Self::open_file(&self.path)?;
+        self.len = OFFSET_SIZE + unsent.len() as u64;
+        self.sent_up_to = OFFSET_SIZE;
+        Ok(())
+    }
+
+    /// Reads the header at `position`, leaving the file at the start of the message
+    fn read_header(&mut self, position: u64) -> io::Result<Option<(u64, u64)>> {
+        let mut header = [0; HEADER_SIZE as usize];
+        self.file.seek(SeekFrom::Start(position))?;
+        match self.file.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let length = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let logs = u32::from_le_bytes(header[4..].try_into().unwrap());
+        Ok(Some((length as u64, logs as u64)))
+    }
+}
+
+/// How often a disconnected logger retries sending its spool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Reconnect {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+/// The socket and spool of the sender thread. While the spool holds anything new messages are
+/// appended to it, so the server receives everything in order once it is back.
+pub(super) struct Outbox {
+    socket: Socket,
+    spool: Option<Spool>,
+    reconnect: Reconnect,
+    backoff: Duration,
+    /// When the spool is sent again, set while it is not empty
+    retry_at: Option<Instant>,
+    counters: Arc<Counters>,
+}
+
+impl Outbox {
+    pub fn new(
+        socket: Socket,
+        spool: Option<Spool>,
+        reconnect: Reconnect,
+        counters: Arc<Counters>,
+    ) -> Self {
+        // Logs a previous run could not send are sent first
+        let retry_at = match &spool {
+            Some(spool) if !spool.is_empty() => {
+                counters.spooled.store(spool.logs(), Ordering::Relaxed);
+                Some(Instant::now())
+            }
+            _ => None,
+        };
+        Self {
+            socket,
+            spool,
+            reconnect,
+            backoff: reconnect.min,
+            retry_at,
+            counters,
+        }
+    }
+
+    /// When the spool should be sent again
+    pub fn retry_at(&self) -> Option<Instant> {
+        self.retry_at
+    }
+
+    /// Sends a message holding `logs` logs, or spools it if the server can't be reached
+    pub fn send(&mut self, message: Vec<u8>, logs: u64) {
+        if self
+            .retry_at
+            .is_some_and(|retry_at| retry_at <= Instant::now())
+        {
+            self.replay();
+        }
+
+        let counters = &self.counters;
+        let Some(spool) = &mut self.spool else {
+            match self.socket.send(message) {
+                Ok(()) => counters.sent.fetch_add(logs, Ordering::Relaxed),
+                Err(_) => counters.failed.fetch_add(logs, Ordering::Relaxed),
+            };
+            return;
+        };
+
+        let message = if spool.is_empty() {
+            match self.socket.send(message) {
+                Ok(()) => {
+                    counters.sent.fetch_add(logs, Ordering::Relaxed);
+                    return;
+                }
+                Err((message, _)) => message.to_vec(),
+            }
+        } else {
+            message
+        };
+        match spool.push(&message, logs) {
+            Ok(true) => {
+                counters.spooled.fetch_add(logs, Ordering::Relaxed);
+                self.retry_at
+                    .get_or_insert_with(|| Instant::now() + self.backoff);
+            }
+            Ok(false) => {
+                counters.dropped.fetch_add(logs, Ordering::Relaxed);
+            }
+            Err(_) => {
+                counters.failed.fetch_add(logs, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Sends the spool in order, backing off until the next try if the server is still away
+    pub fn replay(&mut self) {
+        let Some(spool) = &mut self.spool else {
+            return;
+        };
+        let socket = &self.socket;
+        let spooled = spool.logs();
+        // A spool that can't be read is tried again like a server that can't be reached
+        let done = spool
+            .replay(|message| socket.send(message).is_ok())
+            .unwrap_or(false);
+        let sent = spooled - spool.logs();
+        self.counters.sent.fetch_add(sent, Ordering::Relaxed);
+        self.counters.spooled.fetch_sub(sent, Ordering::Relaxed);
+
+        if done {
+            self.backoff = self.reconnect.min;
+            self.retry_at = None;
+        } else {
+            self.retry_at = Some(Instant::now() + self.backoff);
+            self.backoff = (self.backoff * 2).min(self.reconnect.max);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("heimdall-spool-{name}-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn drain(spool: &mut Spool) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+        assert!(
+            spool
+                .replay(|message| {
+                    messages.push(message);
+                    true
+                })
+                .unwrap()
+        );
+        messages
+    }
+
+    #[test]
+    fn torn_final_record_is_cut_off() {
+        let path = temp_path("torn");
+        let mut spool = Spool::open(path.clone(), 1 << 20).unwrap();
+        assert!(spool.push(b"one", 1).unwrap());
+        assert!(spool.push(b"two", 2).unwrap());
+        drop(spool);
+
+        // The header promises 9 bytes but the write stopped after one
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[9, 0, 0, 0, 1, 0, 0, 0, b'x']).unwrap();
+        drop(file);
+
+        let mut spool = Spool::open(path.clone(), 1 << 20).unwrap();
+        assert_eq!(spool.logs(), 3);
+        assert_eq!(drain(&mut spool), vec![b"one".to_vec(), b"two".to_vec()]);
+        assert!(spool.is_empty());
+        assert_eq!(fs::metadata(&path).unwrap().len(), OFFSET_SIZE);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn partial_replay_is_not_sent_again_after_restart() {
+        let path = temp_path("restart");
+        let mut spool = Spool::open(path.clone(), 1 << 20).unwrap();
+        for message in [b"a", b"b", b"c"] {
+            assert!(spool.push(message, 1).unwrap());
+        }
+        let mut sent = Vec::new();
+        let done = spool
+            .replay(|message| {
+                if sent.is_empty() {
+                    sent.push(message);
+                    true
+                } else {
+                    false
+                }
+            })
+            .unwrap();
+        assert!(!done);
+        assert_eq!(sent, vec![b"a".to_vec()]);
+        drop(spool);
+
+        let mut spool = Spool::open(path.clone(), 1 << 20).unwrap();
+        assert_eq!(spool.logs(), 2);
+        assert_eq!(drain(&mut spool), vec![b"b".to_vec(), b"c".to_vec()]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn file_stays_within_max_bytes() {
+        let path = temp_path("bound");
+        let max_bytes = OFFSET_SIZE + 3 * (HEADER_SIZE + 4);
+        let mut spool = Spool::open(path.clone(), max_bytes).unwrap();
+        for message in [b"aaaa", b"bbbb", b"cccc"] {
+            assert!(spool.push(message, 1).unwrap());
+        }
+        assert!(!spool.push(b"dddd", 1).unwrap());
+        assert!(!spool.push(&[0; 64], 1).unwrap());
+        assert_eq!(fs::metadata(&path).unwrap().len(), max_bytes);
+
+        // Two messages get through before the server goes away again
+        let mut sent = 0;
+        assert!(
+            !spool
+                .replay(|_| {
+                    sent += 1;
+                    sent <= 2
+                })
+                .unwrap()
+        );
+        assert!(spool.push(b"dddd", 1).unwrap());
+        assert!(spool.push(b"eeee", 1).unwrap());
+        assert!(!spool.push(b"ffff", 1).unwrap());
+        assert!(fs::metadata(&path).unwrap().len() <= max_bytes);
+
+        drop(spool);
+        let mut spool = Spool::open(path.clone(), max_bytes).unwrap();
+        assert_eq!(
+            drain(&mut spool),
+            vec![b"cccc".to_vec(), b"dddd".to_vec(), b"eeee".to_vec()]
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,19 +1,140 @@
+/// Location of the macro call site
+#[doc(hidden)]
 #[macro_export]
-macro_rules! log {
-    ($fmt:expr $(, $key:expr => $val:expr)*) => {{
-        let ts = $crate::prelude::current_timestamp();
-        let msg = format!($fmt);
-        let vars = vec![$(($key.to_string(), $val.to_string())),*];
-        $crate::prelude::global_log(ts, msg, vars).unwrap_or_else(|e| {
-            eprintln!("Failed to log message: {}", e);
-        });
+macro_rules! __location {
+    () => {
+        $crate::log::RsLocation {
+            file: file!().to_string(),
+            line: line!(),
+            module: module_path!().to_string(),
+            crate_name: env!("CARGO_CRATE_NAME").to_string(),
+        }
+    };
+}
+
+/// Typed value of a var, or its `Display` text
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __var_value {
+    ($val:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::log::__private::{Displayed as _, Typed as _};
+        (&$crate::log::__private::VarValue(&$val)).rs_value()
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($level:expr; logger: $logger:expr, $fmt:expr $(, $key:expr => $val:expr)*) => {{
+        if const { $level as u8 >= $crate::log::STATIC_MIN_LEVEL as u8 } {
+            let ts = $crate::prelude::current_timestamp();
+            let msg = format!($fmt);
+            let vars = vec![$(($key.to_string(), $crate::__var_value!($val))),*];
+            let location = Some($crate::__location!());
+            $crate::prelude::Logger::log(&$logger, ts, $level, msg, vars, location).unwrap_or_else(|e| {
+                eprintln!("Failed to log message: {}", e);
+            });
+        }
+    }};
+
+    ($level:expr; logger: $logger:expr, $($arg:tt)*) => {{
+        if const { $level as u8 >= $crate::log::STATIC_MIN_LEVEL as u8 } {
+            let ts = $crate::prelude::current_timestamp();
+            let msg = format!($($arg)*);
+            let location = Some($crate::__location!());
+            $crate::prelude::Logger::log(&$logger, ts, $level, msg, Vec::new(), location).unwrap_or_else(|e| {
+                eprintln!("Failed to log message: {}", e);
+            });
+        }
     }};
 
-    ($($arg:tt)*) => {{
-        let ts = $crate::prelude::current_timestamp();
-        let msg = format!($($arg)*);
-        $crate::prelude::global_log(ts, msg, Vec::new()).unwrap_or_else(|e| {
-            eprintln!("Failed to log message: {}", e);
-        });
+    ($level:expr; $fmt:expr $(, $key:expr => $val:expr)*) => {{
+        // Evaluated at compile time, so filtered out levels never format or send anything
+        if const { $level as u8 >= $crate::log::STATIC_MIN_LEVEL as u8 } {
+            let ts = $crate::prelude::current_timestamp();
+            let msg = format!($fmt);
+            let vars = vec![$(($key.to_string(), $crate::__var_value!($val))),*];
+            let location = Some($crate::__location!());
+            $crate::prelude::global_log(ts, $level, msg, vars, location).unwrap_or_else(|e| {
+                eprintln!("Failed to log message: {}", e);
+            });
+        }
     }};
+
+    ($level:expr; $($arg:tt)*) => {{
+        if const { $level as u8 >= $crate::log::STATIC_MIN_LEVEL as u8 } {
+            let ts = $crate::prelude::current_timestamp();
+            let msg = format!($($arg)*);
+            let location = Some($crate::__location!());
+            $crate::prelude::global_log(ts, $level, msg, Vec::new(), location).unwrap_or_else(|e| {
+                eprintln!("Failed to log message: {}", e);
+            });
+        }
+    }};
+}
+
+/// Opens a span named by the format arguments, closed when the returned guard is dropped.
+/// `span!(logger: my_logger, ...)` sends it through `my_logger` instead of the global logger.
+#[macro_export]
+macro_rules! span {
+    (logger: $logger:expr, $($arg:tt)*) => {
+        $crate::span::Span::open(format!($($arg)*), Some($crate::__location!()))
+            .with_logger(&$logger)
+    };
+
+    ($($arg:tt)*) => {
+        $crate::span::Span::open(format!($($arg)*), Some($crate::__location!()))
+    };
+}
+
+/// Logs through the global logger, `log!(logger: my_logger, ...)` uses `my_logger` instead.
+/// The level macros below take the same arguments.
+#[macro_export]
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::prelude::RsLevel::Info; $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::prelude::RsLevel::Trace; $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::prelude::RsLevel::Debug; $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::prelude::RsLevel::Info; $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::prelude::RsLevel::Warn; $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::prelude::RsLevel::Error; $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! fatal {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::prelude::RsLevel::Fatal; $($arg)*)
+    };
 }
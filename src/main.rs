@@ -3,6 +3,7 @@ mod data;
 mod http;
 mod nng;
 mod pipe;
+mod retention;
 mod tui;
 
 use anyhow::{Context, Result};
@@ -20,7 +21,8 @@ fn start_thread(
     closure: impl FnOnce(Arc<Mutex<Data>>) -> Result<()> + Send + 'static,
 ) -> JoinHandle<()> {
     let data_clone = data.clone();
-    tokio::spawn(async move {
+    // Every thread blocks on its own I/O, so keep them off the async workers
+    tokio::task::spawn_blocking(move || {
         data_clone
             .lock()
             .unwrap()
@@ -38,12 +40,18 @@ fn start_thread(
 }
 
 async fn start_server(args: ServerArgs) -> Result<()> {
-    let storage = if let Some(path) = args.sqlite.clone() {
-        Storage::new_sqlite(path.unwrap_or("logs.sqlite".to_string()))
-            .context("Failed to create SQLite storage")?
+    let mut storage = if let Some(path) = args.sqlite.clone() {
+        Storage::new_sqlite_with(
+            path.unwrap_or("logs.sqlite".to_string()),
+            args.sqlite_options(),
+        )
+        .context("Failed to create SQLite storage")?
     } else {
         Storage::new_memory()
     };
+    let retention = args.retention_policy();
+    let enforce_retention = !retention.is_unlimited();
+    storage.set_retention(retention);
     let data: Arc<Mutex<Data>> = Arc::new(Mutex::new(Data::new(
         args.clone(),
         Statuses::new(),
@@ -71,6 +79,16 @@ async fn start_server(args: ServerArgs) -> Result<()> {
         ));
     }
 
+    if enforce_retention {
+        handles.push(start_thread(
+            data.clone(),
+            ThreadType::Retention,
+            move |data| -> Result<()> {
+                retention::run(data).context("Failed to run retention thread")
+            },
+        ));
+    }
+
     if args.tui {
         handles.push(start_thread(
             data.clone(),
@@ -83,6 +101,12 @@ async fn start_server(args: ServerArgs) -> Result<()> {
         let _ = h.await;
     }
 
+    // Also covers logs stored by a receiver that failed before flushing
+    data.lock()
+        .unwrap()
+        .storage
+        .flush()
+        .context("Failed to flush storage")?;
     Ok(())
 }
 
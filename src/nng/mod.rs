@@ -1,18 +1,35 @@
 use anyhow::{Context, Result};
 use nng::{
-    options::{Options, RemAddr},
-    {Pipe, Protocol, Socket},
+    options::{Options, RecvMaxSize, RecvTimeout, RemAddr},
+    {Pipe, Protocol, Socket, SocketAddr},
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
 };
-use std::sync::{Arc, Mutex};
 
 use crate::data::Data;
-use heimdall::{prelude::*, schemas::log::log::Log};
+use heimdall::{log::MAX_MESSAGE_SIZE, prelude::*};
+
+/// How long a receive blocks before the thread checks whether it must terminate
+const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Messages up to this size reach the decoder, which rejects and quarantines those larger than
+/// `MAX_MESSAGE_SIZE`. NNG drops anything larger before it is buffered, so a peer can't make
+/// the server allocate without bound.
+const RECV_MAX_SIZE: usize = 4 * MAX_MESSAGE_SIZE;
 
 pub fn receive(data: Arc<Mutex<Data>>, port: u16) -> Result<()> {
     let bind = format!("tcp://{}:{}", data.lock().unwrap().args.address, port);
     let print_info = !data.lock().unwrap().args.tui;
 
     let mut socket = Socket::new(Protocol::Pull0).context("Failed to create a new socket")?;
+    socket
+        .set_opt::<RecvTimeout>(Some(RECV_TIMEOUT))
+        .context("Failed to set receive timeout")?;
+    socket
+        .set_opt::<RecvMaxSize>(RECV_MAX_SIZE)
+        .context("Failed to set maximum message size")?;
     socket
         .listen(&bind)
         .context("Failed to bind socket to address")?;
@@ -22,51 +39,79 @@ pub fn receive(data: Arc<Mutex<Data>>, port: u16) -> Result<()> {
     }
 
     loop {
-        let must_terminate = {
-            let data_lock = data.lock().unwrap();
-            data_lock.statuses.must_terminate(ThreadType::NNG)
-        };
-        if must_terminate {
-            if print_info {
-                println!("Terminating NNG listener thread");
+        // Blocks for at most RECV_TIMEOUT, so an idle thread sleeps instead of spinning
+        let message = match listen(&mut socket) {
+            Err(e) => {
+                println!("Error: {:?}", e.context("Failed to recive message"));
+                None
             }
-            break;
-        }
+            Ok(message) => message,
+        };
 
-        match listen(&mut socket) {
-            Err(e) => println!("Error: {:?}", e.context("Failed to recive message")),
-            Ok(log) => {
-                if let Some(log) = log {
+        // Lock once per message or timeout: store the logs and check for termination together
+        let mut data_lock = data.lock().unwrap();
+        if let Some((ip, raw)) = message {
+            match RsLog::decode_message(&raw, ip.clone()) {
+                Ok(logs) => {
+                    for log in logs {
+                        if print_info {
+                            println!("{log}");
+                        }
+                        data_lock
+                            .storage
+                            .add_log(log)
+                            .context("Failed to add log to storage")?;
+                    }
+                }
+                Err(e) => {
                     if print_info {
-                        println!("{log}");
+                        println!("Rejected message from {ip}: {e}");
                     }
-                    data.lock()
-                        .unwrap()
+                    data_lock
                         .storage
-                        .add_log(log)
-                        .context("Failed to add log to storage")?;
+                        .reject(&ip, &e.to_string(), &raw)
+                        .context("Failed to quarantine rejected message")?;
                 }
             }
-        };
+        }
+        data_lock
+            .storage
+            .flush_if_due()
+            .context("Failed to flush storage")?;
+        if data_lock.statuses.must_terminate(ThreadType::NNG) {
+            if print_info {
+                println!("Terminating NNG listener thread");
+            }
+            break;
+        }
     }
 
+    data.lock()
+        .unwrap()
+        .storage
+        .flush()
+        .context("Failed to flush storage")?;
     Ok(())
 }
 
-fn listen(socket: &mut Socket) -> Result<Option<RsLog>> {
-    match socket.try_recv() {
-        Ok(mut msg) => {
-            let pipe: Pipe = msg.pipe().context("Message missing pipe")?;
-            let ip = pipe
-                .get_opt::<RemAddr>()
-                .context("Failed to get remote address")?
-                .to_string();
-            let buf: Vec<u8> = msg.as_slice().to_vec();
-            let log =
-                flatbuffers::root::<Log>(&buf).context("Failed to deserialize log message")?;
-            Ok(Some(RsLog::from(log, ip)))
+/// Returns the sender's IP, like the HTTP server uses, and the raw message, or `None` on
+/// timeout
+fn listen(socket: &mut Socket) -> Result<Option<(String, Vec<u8>)>> {
+    match socket.recv() {
+        Ok(msg) => {
+            let ip = msg
+                .pipe()
+                .and_then(|pipe: Pipe| pipe.get_opt::<RemAddr>().ok())
+                .map(|addr| match addr {
+                    // Ports change with every connection, so they are left out
+                    SocketAddr::Inet(addr) => addr.ip().to_string(),
+                    SocketAddr::Inet6(addr) => addr.ip().to_string(),
+                    addr => addr.to_string(),
+                })
+                .unwrap_or_else(|| "unknown".to_string());
+            Ok(Some((ip, msg.as_slice().to_vec())))
         }
-        Err(nng::Error::TryAgain) => Ok(None),
+        Err(nng::Error::TimedOut) => Ok(None),
         Err(e) => Err(e.into()),
     }
 }
@@ -7,7 +7,7 @@ use crate::args::PipeArgs;
 use heimdall::{log, prelude::*};
 
 pub fn pipe(args: PipeArgs) -> Result<()> {
-    Logger::builder()
+    let _guard = Logger::builder()
         .with_address_port(&args.address, args.port)
         .with_app_name("piper")
         .with_version(env!("CARGO_PKG_VERSION"))
@@ -47,7 +47,12 @@ fn pipe_line(line: String, args: &PipeArgs) -> Result<()> {
             println!("No timestamp found in JSON line, using current time.");
             Local::now().into()
         };
-        global_log(ts, line, Vec::new()).context("Failed to log JSON line")?;
+        let level = ["level", "severity", "lvl"]
+            .iter()
+            .find_map(|key| json_log[*key].as_str())
+            .and_then(|level| level.parse::<RsLevel>().ok())
+            .unwrap_or_default();
+        global_log(ts, level, line, Vec::new(), None).context("Failed to log JSON line")?;
     } else {
         log!("{}", line);
     }
@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::data::Data;
+use heimdall::prelude::*;
+
+/// How often the thread wakes up to check whether it must terminate
+const TICK: Duration = Duration::from_millis(100);
+/// How often the retention policy is enforced
+const ENFORCE_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the storage is vacuumed, if anything was evicted since the last time
+const VACUUM_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub fn run(data: Arc<Mutex<Data>>) -> Result<()> {
+    let print_info = !data.lock().unwrap().args.tui;
+
+    let mut last_enforced = Instant::now();
+    let mut last_vacuumed = Instant::now();
+    let mut evicted_since_vacuum = 0;
+
+    loop {
+        thread::sleep(TICK);
+
+        let mut data_lock = data.lock().unwrap();
+        if data_lock.statuses.must_terminate(ThreadType::Retention) {
+            if print_info {
+                println!("Terminating retention thread");
+            }
+            break;
+        }
+
+        if last_enforced.elapsed() < ENFORCE_INTERVAL {
+            continue;
+        }
+        last_enforced = Instant::now();
+
+        let evicted = data_lock
+            .storage
+            .enforce_retention()
+            .context("Failed to enforce the retention policy")?;
+        if evicted > 0 && print_info {
+            println!("Evicted {evicted} logs");
+        }
+        evicted_since_vacuum += evicted;
+
+        if evicted_since_vacuum > 0 && last_vacuumed.elapsed() >= VACUUM_INTERVAL {
+            data_lock
+                .storage
+                .vacuum()
+                .context("Failed to vacuum the storage")?;
+            last_vacuumed = Instant::now();
+            evicted_since_vacuum = 0;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, FixedOffset};
+use std::{
+    cell::RefCell,
+    hash::{BuildHasher, Hasher, RandomState},
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::logger::{Logger, global_send};
+use crate::prelude::{RsContext, RsKind, RsLevel, RsLocation, RsLog, RsTrace, current_timestamp};
+
+pub mod prelude {
+    pub use super::Span;
+}
+
+thread_local! {
+    /// Spans open on this thread, innermost last
+    static OPEN_SPANS: RefCell<Vec<RsTrace>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A unit of work that logs can be grouped by. Logs made on the thread that opened it are part
+/// of it until it is closed, which sends it with how long it was open.
+#[must_use = "a span is closed as soon as it is dropped"]
+pub struct Span {
+    name: String,
+    level: RsLevel,
+    trace: RsTrace,
+    ts: DateTime<FixedOffset>,
+    started: Instant,
+    location: Option<RsLocation>,
+    /// Sent through the global logger if not set
+    logger: Option<Logger>,
+    /// Keeps the span on the thread that opened it, as closing it elsewhere would leave it open
+    /// in that thread's spans and tag every later log made there with it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Span {
+    /// Opens a span inside the innermost span open on this thread, or starts a new trace
+    pub fn open(name: impl Into<String>, location: Option<RsLocation>) -> Self {
+        let trace = match current() {
+            Some(parent) => RsTrace {
+                trace_id: parent.trace_id,
+                span_id: new_span_id(),
+                parent_span_id: Some(parent.span_id),
+            },
+            None => RsTrace {
+                trace_id: new_trace_id(),
+                span_id: new_span_id(),
+                parent_span_id: None,
+            },
+        };
+        Self::enter(name.into(), trace, location)
+    }
+
+    /// Opens a span in a trace started elsewhere, usually by the service that sent the request
+    /// being handled. `parent_span_id` is the span that sent it.
+    pub fn open_remote(
+        name: impl Into<String>,
+        trace_id: impl Into<String>,
+        parent_span_id: Option<String>,
+        location: Option<RsLocation>,
+    ) -> Self {
+        let trace = RsTrace {
+            trace_id: trace_id.into(),
+            span_id: new_span_id(),
+            parent_span_id,
+        };
+        Self::enter(name.into(), trace, location)
+    }
+
+    fn enter(name: String, trace: RsTrace, location: Option<RsLocation>) -> Self {
+        OPEN_SPANS.with_borrow_mut(|spans| spans.push(trace.clone()));
+        Self {
+            name,
+            level: RsLevel::Info,
+            trace,
+            ts: current_timestamp(),
+            started: Instant::now(),
+            location,
+            logger: None,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Sends the span through `logger` instead of the global logger
+    pub fn with_logger(mut self, logger: &Logger) -> Self {
+        self.logger = Some(logger.clone());
+        self
+    }
+
+    pub fn with_level(mut self, level: RsLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn trace_id(&self) -> &str {
+        &self.trace.trace_id
+    }
+
+    pub fn span_id(&self) -> &str {
+        &self.trace.span_id
+    }
+
+    /// Same as dropping the span
+    pub fn close(self) {}
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        // Spans are not always closed in the order they were opened
+        OPEN_SPANS.with_borrow_mut(|spans| {
+            if let Some(index) = spans
+                .iter()
+                .rposition(|trace| trace.span_id == self.trace.span_id)
+            {
+                spans.remove(index);
+            }
+        });
+
+        let mut log = RsLog::new(
+            self.ts,
+            self.level,
+            std::mem::take(&mut self.name),
+            RsContext::default(),
+            Vec::new(),
+            self.location.take(),
+        );
+        log.trace = Some(self.trace.clone());
+        log.kind = RsKind::Span {
+            duration: self.started.elapsed(),
+        };
+        let sent = match &self.logger {
+            Some(logger) => logger.send(log),
+            None => global_send(log),
+        };
+        sent.unwrap_or_else(|e| {
+            eprintln!("Failed to send span: {}", e);
+        });
+    }
+}
+
+/// Trace position of a log made on this thread right now
+pub fn current() -> Option<RsTrace> {
+    OPEN_SPANS.with_borrow(|spans| {
+        spans.last().map(|span| RsTrace {
+            trace_id: span.trace_id.clone(),
+            span_id: span.span_id.clone(),
+            parent_span_id: None,
+        })
+    })
+}
+
+/// 128 random bits in hex, like W3C trace context ids
+pub fn new_trace_id() -> String {
+    format!("{:016x}{:016x}", random_u64(), random_u64())
+}
+
+/// 64 random bits in hex
+pub fn new_span_id() -> String {
+    format!("{:016x}", random_u64())
+}
+
+/// Every `RandomState` is seeded differently, the counter and time only guard against a
+/// platform that seeds them poorly
+fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos()),
+    );
+    hasher.finish()
+}
@@ -9,6 +9,7 @@ pub enum ThreadType {
     TUI,
     NNG,
     HTTP,
+    Retention,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -0,0 +1,47 @@
+use rusqlite::ErrorCode;
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+#[derive(Debug)]
+pub enum StorageError {
+    /// The backend could not be read or written, e.g. a locked database or a full disk
+    Io(rusqlite::Error),
+    /// Stored data is damaged
+    Corruption(String),
+    /// The database was created by a newer heimdall
+    SchemaMismatch { found: usize, supported: usize },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "Storage I/O failed: {e}"),
+            StorageError::Corruption(reason) => write!(f, "Storage is corrupted: {reason}"),
+            StorageError::SchemaMismatch { found, supported } => write!(
+                f,
+                "Database schema version {found} is newer than version {supported} supported by this heimdall, upgrade heimdall to open it"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e.sqlite_error_code() {
+            Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => {
+                StorageError::Corruption(e.to_string())
+            }
+            _ => StorageError::Io(e),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, FixedOffset};
+use std::collections::VecDeque;
+
+use super::{LogQuery, LogStore, QUARANTINE_CAPACITY, QuarantinedMessage, Result};
+use crate::prelude::{RsLog, RsValue, RsVar};
+
+/// Keeps logs in a queue, oldest first. Ids keep counting up when old logs are evicted.
+#[derive(Default)]
+pub struct MemoryStore {
+    logs: VecDeque<(usize, RsLog)>,
+    next_id: usize,
+    bytes: u64,
+    quarantine: VecDeque<QuarantinedMessage>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl LogStore for MemoryStore {
+    fn add_log(&mut self, log: RsLog) -> Result<usize> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.bytes += log_size(&log);
+        self.logs.push_back((id, log));
+        Ok(id)
+    }
+
+    fn logs_amount(&self) -> Result<usize> {
+        Ok(self.logs.len())
+    }
+
+    fn get_log(&self, index: usize) -> Result<Option<RsLog>> {
+        Ok(self.logs.get(index).map(|(_, log)| log.clone()))
+    }
+
+    fn get_log_by_id(&self, id: usize) -> Result<Option<RsLog>> {
+        // Ids are increasing, so the queue is sorted by them
+        Ok(self
+            .logs
+            .binary_search_by_key(&id, |(id, _)| *id)
+            .ok()
+            .map(|index| self.logs[index].1.clone()))
+    }
+
+    fn get_visible_logs(&self, start: usize, amount: usize) -> Result<Vec<(usize, RsLog)>> {
+        let total = self.logs.len();
+        let end = total.saturating_sub(start);
+        let start_idx = end.saturating_sub(amount);
+        Ok(self.logs.range(start_idx..end).cloned().collect())
+    }
+
+    fn search(&self, query: &str, offset: usize, limit: usize) -> Result<Vec<usize>> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Rank by how often the terms occur, every term has to be present
+        let mut scored: Vec<(usize, usize)> = self
+            .logs
+            .iter()
+            .filter_map(|(id, log)| {
+                let mut text = log.msg.to_lowercase();
+                for var in &log.vars {
+                    text.push(' ');
+                    text.push_str(&var.key.to_lowercase());
+                    text.push(' ');
+                    text.push_str(&var.val.to_string().to_lowercase());
+                }
+                let counts: Vec<usize> = terms
+                    .iter()
+                    .map(|term| text.matches(term.as_str()).count())
+                    .collect();
+                (!counts.contains(&0)).then(|| (counts.iter().sum(), *id))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.cmp(a));
+
+        Ok(scored
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(_, id)| id)
+            .collect())
+    }
+
+    fn count(&self, query: &LogQuery) -> Result<usize> {
+        Ok(self
+            .logs
+            .iter()
+            .filter(|(_, log)| query.matches(log))
+            .count())
+    }
+
+    fn query(&self, query: &LogQuery, offset: usize, limit: usize) -> Result<Vec<(usize, RsLog)>> {
+        Ok(self
+            .logs
+            .iter()
+            .rev()
+            .filter(|(_, log)| query.matches(log))
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    fn evict_oldest(&mut self, amount: usize) -> Result<usize> {
+        let amount = amount.min(self.logs.len());
+        for (_, log) in self.logs.drain(..amount) {
+            self.bytes -= log_size(&log);
+        }
+        Ok(amount)
+    }
+
+    fn evict_before(&mut self, ts: DateTime<FixedOffset>) -> Result<usize> {
+        let before = self.logs.len();
+        let mut freed = 0;
+        self.logs.retain(|(_, log)| {
+            let keep = log.ts >= ts;
+            if !keep {
+                freed += log_size(log);
+            }
+            keep
+        });
+        self.bytes -= freed;
+        Ok(before - self.logs.len())
+    }
+
+    fn size_bytes(&self) -> Result<u64> {
+        Ok(self.bytes)
+    }
+
+    fn quarantine(&mut self, message: QuarantinedMessage) -> Result<()> {
+        if self.quarantine.len() >= QUARANTINE_CAPACITY {
+            self.quarantine.pop_front();
+        }
+        self.quarantine.push_back(message);
+        Ok(())
+    }
+
+    fn quarantined(&self, offset: usize, limit: usize) -> Result<Vec<QuarantinedMessage>> {
+        Ok(self
+            .quarantine
+            .iter()
+            .rev()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Rough heap and inline size of a log, used for byte caps
+fn log_size(log: &RsLog) -> u64 {
+    let strings = log.msg.len()
+        + log.ip.len()
+        + log.context.app.len()
+        + log.context.os.len()
+        + log.context.version.len()
+        + log
+            .vars
+            .iter()
+            .map(|var| {
+                var.key.len()
+                    + match &var.val {
+                        RsValue::Str(value) => value.len(),
+                        RsValue::Bytes(value) => value.len(),
+                        _ => 0,
+                    }
+            })
+            .sum::<usize>()
+        + log.location.as_ref().map_or(0, |location| {
+            location.file.len() + location.module.len() + location.crate_name.len()
+        })
+        + log.trace.as_ref().map_or(0, |trace| {
+            trace.trace_id.len()
+                + trace.span_id.len()
+                + trace.parent_span_id.as_ref().map_or(0, String::len)
+        });
+    (size_of::<(usize, RsLog)>() + log.vars.len() * size_of::<RsVar>() + strings) as u64
+}
@@ -0,0 +1,293 @@
+mod error;
+mod memory;
+mod quarantine;
+mod query;
+mod retention;
+mod sqlite;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use std::{
+    collections::HashMap,
+    sync::mpsc::{Receiver, SyncSender, sync_channel},
+};
+
+use crate::prelude::RsLog;
+
+pub use error::{Result, StorageError};
+pub use memory::MemoryStore;
+pub use quarantine::{
+    QUARANTINE_CAPACITY, QUARANTINE_RAW_LIMIT, QuarantinedMessage, REJECTED_PEERS_CAPACITY,
+};
+pub use query::{LogQuery, MessageFilter, VarFilter, VarOp};
+pub use retention::RetentionPolicy;
+pub use sqlite::{Durability, SqliteOptions, SqliteStore};
+
+pub mod prelude {
+    pub use super::{
+        Durability, LogQuery, LogStore, MemoryStore, MessageFilter, QuarantinedMessage,
+        RetentionPolicy, SqliteOptions, SqliteStore, Storage, StorageError, TracePage, VarFilter,
+        VarOp,
+    };
+}
+
+/// How many logs a subscriber may fall behind before it is dropped
+pub const SUBSCRIBER_CAPACITY: usize = 1024;
+
+/// Part of the logs of a trace
+pub struct TracePage {
+    pub logs: Vec<(usize, RsLog)>,
+    /// How many logs the whole trace has
+    pub total: usize,
+}
+
+/// A place logs are kept in. Ids are assigned by the store when a log is added.
+pub trait LogStore: Send {
+    /// Stores a log and returns its id. Stores that buffer writes may not show it until flushed.
+    fn add_log(&mut self, log: RsLog) -> Result<usize>;
+    /// Writes out every buffered log
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Writes out buffered logs if they have waited long enough, called regularly by receivers
+    fn flush_if_due(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn logs_amount(&self) -> Result<usize>;
+    fn get_log(&self, index: usize) -> Result<Option<RsLog>>;
+    fn get_log_by_id(&self, id: usize) -> Result<Option<RsLog>>;
+    fn get_visible_logs(&self, start: usize, amount: usize) -> Result<Vec<(usize, RsLog)>>;
+    /// Full-text search over messages and vars, returns ids with the best matches first
+    fn search(&self, query: &str, offset: usize, limit: usize) -> Result<Vec<usize>>;
+    /// Counts the logs matching `query`
+    fn count(&self, query: &LogQuery) -> Result<usize>;
+    /// Returns up to `limit` logs matching `query`, newest first, skipping the first `offset`
+    fn query(&self, query: &LogQuery, offset: usize, limit: usize) -> Result<Vec<(usize, RsLog)>>;
+    /// Up to `limit` logs of a trace, spans included, skipping the first `offset` stored. The
+    /// page is ordered by timestamp.
+    fn get_trace(&self, trace_id: &str, offset: usize, limit: usize) -> Result<TracePage> {
+        let query = LogQuery {
+            trace_id: Some(trace_id.to_string()),
+            ..LogQuery::default()
+        };
+        let total = self.count(&query)?;
+        // Pages start from the oldest logs, while `query` starts from the newest
+        let end = total.saturating_sub(offset);
+        let start = end.saturating_sub(limit);
+        let mut logs = self.query(&query, start, end - start)?;
+        // Spans are sent when they close, after the logs made inside them
+        logs.sort_by_key(|(id, log)| (log.ts, *id));
+        Ok(TracePage { logs, total })
+    }
+    /// Removes up to `amount` of the oldest logs and returns how many were removed
+    fn evict_oldest(&mut self, amount: usize) -> Result<usize>;
+    /// Removes every log with a timestamp before `ts` and returns how many were removed
+    fn evict_before(&mut self, ts: DateTime<FixedOffset>) -> Result<usize>;
+    /// Approximate number of bytes the stored logs take up, including what is kept alongside
+    /// them to query them but not the quarantine
+    fn size_bytes(&self) -> Result<u64>;
+    /// Gives the space freed by evictions back, if the store holds on to it
+    fn vacuum(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Keeps a rejected message, dropping the oldest beyond `QUARANTINE_CAPACITY`
+    fn quarantine(&mut self, message: QuarantinedMessage) -> Result<()>;
+    /// Returns up to `limit` quarantined messages after skipping `offset`, newest first
+    fn quarantined(&self, offset: usize, limit: usize) -> Result<Vec<QuarantinedMessage>>;
+    /// How many stored logs could not be decoded and were left out of results
+    fn skipped_rows(&self) -> usize {
+        0
+    }
+}
+
+pub struct Storage {
+    store: Box<dyn LogStore>,
+    subscribers: Vec<SyncSender<(usize, RsLog)>>,
+    retention: RetentionPolicy,
+    evicted: usize,
+    rejected: HashMap<String, usize>,
+    pub updated: bool,
+}
+
+impl Storage {
+    pub fn new(store: Box<dyn LogStore>) -> Self {
+        Self {
+            store,
+            subscribers: Vec::new(),
+            retention: RetentionPolicy::default(),
+            evicted: 0,
+            rejected: HashMap::new(),
+            updated: true, // Start at updated state so that the renderer fetches all logs
+        }
+    }
+
+    pub fn new_memory() -> Self {
+        Self::new(Box::new(MemoryStore::new()))
+    }
+
+    pub fn new_sqlite(path: impl Into<String>) -> Result<Self> {
+        Ok(Self::new(Box::new(SqliteStore::open(path)?)))
+    }
+
+    pub fn new_sqlite_with(path: impl Into<String>, options: SqliteOptions) -> Result<Self> {
+        Ok(Self::new(Box::new(SqliteStore::open_with(path, options)?)))
+    }
+
+    /// Stores a log and returns its id
+    pub fn add_log(&mut self, log: RsLog) -> Result<usize> {
+        let published = (!self.subscribers.is_empty()).then(|| log.clone());
+        let id = self.store.add_log(log)?;
+        if let Some(log) = published {
+            // Receivers that hung up or fell too far behind are dropped, so a slow one can't
+            // hold up storing logs or make its queue grow without limit
+            self.subscribers
+                .retain(|subscriber| subscriber.try_send((id, log.clone())).is_ok());
+        }
+        self.updated = true;
+        Ok(id)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.store.flush()
+    }
+
+    pub fn flush_if_due(&mut self) -> Result<()> {
+        self.store.flush_if_due()
+    }
+
+    /// Returns a channel that receives every log stored from now on, with its id. The channel
+    /// is closed once it holds `SUBSCRIBER_CAPACITY` logs that weren't received yet.
+    pub fn subscribe(&mut self) -> Receiver<(usize, RsLog)> {
+        let (sender, receiver) = sync_channel(SUBSCRIBER_CAPACITY);
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn was_updated(&self) -> bool {
+        self.updated
+    }
+
+    pub fn logs_amount(&self) -> Result<usize> {
+        self.store.logs_amount()
+    }
+
+    pub fn get_log(&self, index: usize) -> Result<Option<RsLog>> {
+        self.store.get_log(index)
+    }
+
+    pub fn get_log_by_id(&self, id: usize) -> Result<Option<RsLog>> {
+        self.store.get_log_by_id(id)
+    }
+
+    pub fn get_visible_logs(&self, start: usize, amount: usize) -> Result<Vec<(usize, RsLog)>> {
+        self.store.get_visible_logs(start, amount)
+    }
+
+    pub fn search(&self, query: &str, offset: usize, limit: usize) -> Result<Vec<usize>> {
+        self.store.search(query, offset, limit)
+    }
+
+    pub fn count(&self, query: &LogQuery) -> Result<usize> {
+        self.store.count(query)
+    }
+
+    pub fn query(
+        &self,
+        query: &LogQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<(usize, RsLog)>> {
+        self.store.query(query, offset, limit)
+    }
+
+    pub fn get_trace(&self, trace_id: &str, offset: usize, limit: usize) -> Result<TracePage> {
+        self.store.get_trace(trace_id, offset, limit)
+    }
+
+    pub fn set_retention(&mut self, retention: RetentionPolicy) {
+        self.retention = retention;
+    }
+
+    pub fn retention(&self) -> &RetentionPolicy {
+        &self.retention
+    }
+
+    /// Counts a message from `ip` that could not be decoded and quarantines its bytes
+    pub fn reject(&mut self, ip: &str, reason: &str, raw: &[u8]) -> Result<()> {
+        if !self.rejected.contains_key(ip)
+            && self.rejected.len() >= REJECTED_PEERS_CAPACITY
+            && let Some(fewest) = self
+                .rejected
+                .iter()
+                .min_by_key(|(_, count)| **count)
+                .map(|(ip, _)| ip.clone())
+        {
+            self.rejected.remove(&fewest);
+        }
+        *self.rejected.entry(ip.to_string()).or_default() += 1;
+        self.store.quarantine(QuarantinedMessage {
+            ts: Utc::now().fixed_offset(),
+            ip: ip.to_string(),
+            reason: reason.to_string(),
+            raw: raw[..raw.len().min(QUARANTINE_RAW_LIMIT)].to_vec(),
+        })
+    }
+
+    /// Rejected messages since the server started, per peer IP, for at most
+    /// `REJECTED_PEERS_CAPACITY` peers
+    pub fn rejected(&self) -> &HashMap<String, usize> {
+        &self.rejected
+    }
+
+    pub fn quarantined(&self, offset: usize, limit: usize) -> Result<Vec<QuarantinedMessage>> {
+        self.store.quarantined(offset, limit)
+    }
+
+    pub fn skipped_rows(&self) -> usize {
+        self.store.skipped_rows()
+    }
+
+    /// Total number of logs evicted by the retention policy so far
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    /// Evicts the oldest logs until the retention policy holds, returns how many were evicted
+    pub fn enforce_retention(&mut self) -> Result<usize> {
+        let mut evicted = 0;
+
+        if let Some(max_age) = self.retention.max_age {
+            let cutoff = (Utc::now() - max_age).fixed_offset();
+            evicted += self.store.evict_before(cutoff)?;
+        }
+
+        if let Some(max_records) = self.retention.max_records {
+            let amount = self.store.logs_amount()?;
+            if amount > max_records {
+                evicted += self.store.evict_oldest(amount - max_records)?;
+            }
+        }
+
+        if let Some(max_bytes) = self.retention.max_bytes {
+            // Sizes are only known in total, so estimate how many logs to drop from the average.
+            // Freed space may show up late (index segments are merged lazily), so this is done
+            // once per call and the next call corrects the estimate instead of evicting more now.
+            let size = self.store.size_bytes()?;
+            let amount = self.store.logs_amount()? as u64;
+            if size > max_bytes && amount > 0 {
+                let average = (size / amount).max(1);
+                let excess = (size - max_bytes).div_ceil(average);
+                evicted += self.store.evict_oldest(excess as usize)?;
+            }
+        }
+
+        if evicted > 0 {
+            self.evicted += evicted;
+            self.updated = true;
+        }
+        Ok(evicted)
+    }
+
+    pub fn vacuum(&mut self) -> Result<()> {
+        self.store.vacuum()
+    }
+}
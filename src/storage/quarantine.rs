@@ -0,0 +1,22 @@
+use chrono::{DateTime, FixedOffset};
+
+/// How many rejected messages a store keeps for inspection, older ones are dropped
+pub const QUARANTINE_CAPACITY: usize = 1000;
+
+/// How many bytes of a rejected message are kept, enough to tell what was sent without
+/// letting peers fill the quarantine with large payloads
+pub const QUARANTINE_RAW_LIMIT: usize = 4 * 1024;
+
+/// How many peers rejected messages are counted for, the peer with the fewest is forgotten to
+/// make room for a new one
+pub const REJECTED_PEERS_CAPACITY: usize = 1024;
+
+/// A received message that could not be decoded, kept as raw bytes cut to
+/// `QUARANTINE_RAW_LIMIT`
+#[derive(Debug, Clone)]
+pub struct QuarantinedMessage {
+    pub ts: DateTime<FixedOffset>,
+    pub ip: String,
+    pub reason: String,
+    pub raw: Vec<u8>,
+}
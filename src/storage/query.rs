@@ -0,0 +1,309 @@
+use chrono::{DateTime, FixedOffset};
+use regex::Regex;
+
+use std::cmp::Ordering;
+
+use crate::prelude::{RsLevel, RsLocation, RsLog, RsValue};
+
+/// How a log's message is matched
+#[derive(Debug, Clone)]
+pub enum MessageFilter {
+    Contains(String),
+    Regex(Regex),
+}
+
+impl MessageFilter {
+    pub fn matches(&self, msg: &str) -> bool {
+        match self {
+            MessageFilter::Contains(needle) => msg.contains(needle.as_str()),
+            MessageFilter::Regex(regex) => regex.is_match(msg),
+        }
+    }
+}
+
+/// How a var is compared with the value of a `VarFilter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarOp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Predicate on the var named `key`
+#[derive(Debug, Clone)]
+pub struct VarFilter {
+    pub key: String,
+    pub op: VarOp,
+    pub value: RsValue,
+}
+
+impl VarFilter {
+    pub fn new(key: impl Into<String>, op: VarOp, value: RsValue) -> Self {
+        Self {
+            key: key.into(),
+            op,
+            value,
+        }
+    }
+
+    /// Equality also holds between a text var and the text form of the value, so that vars
+    /// logged before values were typed still match. Orderings only hold between comparable
+    /// types. Both follow how the SQLite store compares values.
+    pub fn matches(&self, val: &RsValue) -> bool {
+        if self.op == VarOp::Eq {
+            let text_var = matches!(val, RsValue::Str(_) | RsValue::Timestamp(_));
+            return sql_eq(val, &self.value)
+                || (text_var && val.to_string() == self.value.to_string());
+        }
+        let Some(ordering) = val.compare(&self.value) else {
+            return false;
+        };
+        match self.op {
+            VarOp::Eq => ordering == Ordering::Equal,
+            VarOp::Lt => ordering == Ordering::Less,
+            VarOp::Le => ordering != Ordering::Greater,
+            VarOp::Gt => ordering == Ordering::Greater,
+            VarOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Equality of two values as stored in SQLite: booleans are integers, numbers compare by value
+/// whether they are integers or floats, timestamps are their text and numbers never equal text
+fn sql_eq(a: &RsValue, b: &RsValue) -> bool {
+    let integer = |value: &RsValue| match value {
+        RsValue::Int(value) => Some(*value),
+        RsValue::Bool(value) => Some(*value as i64),
+        _ => None,
+    };
+    let number = |value: &RsValue| match value {
+        RsValue::Float(value) => Some(*value),
+        _ => integer(value).map(|value| value as f64),
+    };
+    let text = |value: &RsValue| match value {
+        RsValue::Str(_) | RsValue::Timestamp(_) => Some(value.to_string()),
+        _ => None,
+    };
+
+    match (a, b) {
+        (RsValue::Bytes(a), RsValue::Bytes(b)) => a == b,
+        _ => {
+            if let (Some(a), Some(b)) = (integer(a), integer(b)) {
+                a == b
+            } else if let (Some(a), Some(b)) = (number(a), number(b)) {
+                a == b
+            } else {
+                matches!((text(a), text(b)), (Some(a), Some(b)) if a == b)
+            }
+        }
+    }
+}
+
+/// Filter over stored logs. Empty fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery {
+    /// Inclusive lower bound of the timestamp
+    pub from: Option<DateTime<FixedOffset>>,
+    /// Exclusive upper bound of the timestamp
+    pub to: Option<DateTime<FixedOffset>>,
+    pub app: Option<String>,
+    pub version: Option<String>,
+    pub pid: Option<u32>,
+    pub ip: Option<String>,
+    /// Least severe level to include
+    pub level: Option<RsLevel>,
+    pub msg: Option<MessageFilter>,
+    /// Predicates that must all hold for some var of the log
+    pub vars: Vec<VarFilter>,
+    /// Source file, as given by `file!()`
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// Module path, also matching the modules inside it
+    pub module: Option<String>,
+    pub crate_name: Option<String>,
+    pub trace_id: Option<String>,
+}
+
+impl LogQuery {
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none()
+            && self.to.is_none()
+            && self.app.is_none()
+            && self.version.is_none()
+            && self.pid.is_none()
+            && self.ip.is_none()
+            && self.level.is_none()
+            && self.msg.is_none()
+            && self.vars.is_empty()
+            && self.file.is_none()
+            && self.line.is_none()
+            && self.module.is_none()
+            && self.crate_name.is_none()
+            && self.trace_id.is_none()
+    }
+
+    pub fn matches(&self, log: &RsLog) -> bool {
+        self.from.is_none_or(|from| log.ts >= from)
+            && self.to.is_none_or(|to| log.ts < to)
+            && self.app.as_ref().is_none_or(|app| log.context.app == *app)
+            && self
+                .version
+                .as_ref()
+                .is_none_or(|version| log.context.version == *version)
+            && self.pid.is_none_or(|pid| log.context.pid == pid)
+            && self.ip.as_ref().is_none_or(|ip| log.ip == *ip)
+            && self.level.is_none_or(|level| log.level >= level)
+            && self.msg.as_ref().is_none_or(|msg| msg.matches(&log.msg))
+            && self.vars.iter().all(|filter| {
+                log.vars
+                    .iter()
+                    .any(|var| var.key == filter.key && filter.matches(&var.val))
+            })
+            && self.matches_location(log.location.as_ref())
+            && self.trace_id.as_ref().is_none_or(|trace_id| {
+                log.trace
+                    .as_ref()
+                    .is_some_and(|trace| trace.trace_id == *trace_id)
+            })
+    }
+
+    fn matches_location(&self, location: Option<&RsLocation>) -> bool {
+        let Some(location) = location else {
+            return self.file.is_none()
+                && self.line.is_none()
+                && self.module.is_none()
+                && self.crate_name.is_none();
+        };
+        self.file.as_ref().is_none_or(|file| location.file == *file)
+            && self.line.is_none_or(|line| location.line == line)
+            && self.module.as_ref().is_none_or(|module| {
+                location
+                    .module
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            && self
+                .crate_name
+                .as_ref()
+                .is_none_or(|crate_name| location.crate_name == *crate_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::RsContext;
+    use crate::storage::{LogStore, MemoryStore, SqliteStore};
+
+    /// Each log is named after the only var it has, `v`
+    fn stores() -> Vec<Box<dyn LogStore>> {
+        let vars = [
+            ("int 1", RsValue::Int(1)),
+            ("int 5", RsValue::Int(5)),
+            ("float 1.5", RsValue::Float(1.5)),
+            ("float 5", RsValue::Float(5.0)),
+            ("bool true", RsValue::Bool(true)),
+            ("bool false", RsValue::Bool(false)),
+            ("str 1", RsValue::Str("1".to_string())),
+            ("str 5", RsValue::Str("5".to_string())),
+            ("str b", RsValue::Str("b".to_string())),
+            ("str true", RsValue::Str("true".to_string())),
+        ];
+        let mut stores: Vec<Box<dyn LogStore>> = vec![
+            Box::new(MemoryStore::new()),
+            Box::new(SqliteStore::open(":memory:").unwrap()),
+        ];
+        for store in &mut stores {
+            for (msg, val) in &vars {
+                let log = RsLog::new(
+                    DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap(),
+                    RsLevel::Info,
+                    msg.to_string(),
+                    RsContext::default(),
+                    vec![("v".to_string(), val.clone())],
+                    None,
+                );
+                store.add_log(log).unwrap();
+            }
+            store.flush().unwrap();
+        }
+        stores
+    }
+
+    /// Checks that both stores return exactly the `expected` logs
+    fn assert_filter(op: VarOp, value: RsValue, expected: &[&str]) {
+        let query = LogQuery {
+            vars: vec![VarFilter::new("v", op, value)],
+            ..LogQuery::default()
+        };
+        for store in stores() {
+            let mut found: Vec<String> = store
+                .query(&query, 0, 100)
+                .unwrap()
+                .into_iter()
+                .map(|(_, log)| log.msg)
+                .collect();
+            found.sort();
+            let mut expected = expected.to_vec();
+            expected.sort();
+            assert_eq!(found, expected, "{:?}", query.vars[0]);
+        }
+    }
+
+    #[test]
+    fn int_filters() {
+        assert_filter(VarOp::Eq, RsValue::Int(1), &["int 1", "bool true", "str 1"]);
+        assert_filter(VarOp::Eq, RsValue::Int(5), &["int 5", "float 5", "str 5"]);
+        assert_filter(
+            VarOp::Gt,
+            RsValue::Int(1),
+            &["int 5", "float 1.5", "float 5"],
+        );
+        assert_filter(VarOp::Le, RsValue::Int(1), &["int 1"]);
+    }
+
+    #[test]
+    fn float_filters() {
+        assert_filter(VarOp::Eq, RsValue::Float(1.5), &["float 1.5"]);
+        assert_filter(
+            VarOp::Eq,
+            RsValue::Float(5.0),
+            &["int 5", "float 5", "str 5"],
+        );
+        assert_filter(VarOp::Lt, RsValue::Float(1.5), &["int 1"]);
+        assert_filter(
+            VarOp::Ge,
+            RsValue::Float(1.5),
+            &["float 1.5", "int 5", "float 5"],
+        );
+    }
+
+    #[test]
+    fn bool_filters() {
+        assert_filter(
+            VarOp::Eq,
+            RsValue::Bool(true),
+            &["bool true", "int 1", "str true"],
+        );
+        assert_filter(VarOp::Eq, RsValue::Bool(false), &["bool false"]);
+        assert_filter(VarOp::Gt, RsValue::Bool(false), &[]);
+    }
+
+    #[test]
+    fn string_filters() {
+        assert_filter(VarOp::Eq, RsValue::Str("1".to_string()), &["str 1"]);
+        assert_filter(VarOp::Eq, RsValue::Str("b".to_string()), &["str b"]);
+        assert_filter(
+            VarOp::Lt,
+            RsValue::Str("b".to_string()),
+            &["str 1", "str 5"],
+        );
+        assert_filter(
+            VarOp::Ge,
+            RsValue::Str("5".to_string()),
+            &["str 5", "str b", "str true"],
+        );
+    }
+}
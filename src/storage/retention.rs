@@ -0,0 +1,118 @@
+use chrono::Duration;
+
+/// Limits on how much a store keeps, the oldest logs are evicted first. `None` means unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    pub max_records: Option<usize>,
+    pub max_age: Option<Duration>,
+    pub max_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_records.is_none() && self.max_age.is_none() && self.max_bytes.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{RsContext, RsLevel, RsLog};
+    use crate::storage::{LogQuery, Storage};
+    use chrono::Utc;
+
+    fn log(msg: &str, age: Duration) -> RsLog {
+        RsLog::new(
+            (Utc::now() - age).fixed_offset(),
+            RsLevel::Info,
+            msg.to_string(),
+            RsContext::default(),
+            Vec::new(),
+            None,
+        )
+    }
+
+    fn storage(retention: RetentionPolicy, logs: &[RsLog]) -> Storage {
+        let mut storage = Storage::new_memory();
+        storage.set_retention(retention);
+        for log in logs {
+            storage.add_log(log.clone()).unwrap();
+        }
+        storage
+    }
+
+    /// Messages of the kept logs, oldest first
+    fn messages(storage: &Storage) -> Vec<String> {
+        let logs = storage.query(&LogQuery::default(), 0, usize::MAX).unwrap();
+        logs.into_iter().rev().map(|(_, log)| log.msg).collect()
+    }
+
+    #[test]
+    fn oldest_logs_beyond_the_count_are_evicted() {
+        let logs: Vec<_> = (0..5)
+            .map(|i| log(&i.to_string(), Duration::zero()))
+            .collect();
+        let mut storage = storage(
+            RetentionPolicy {
+                max_records: Some(3),
+                ..RetentionPolicy::default()
+            },
+            &logs,
+        );
+
+        assert_eq!(storage.enforce_retention().unwrap(), 2);
+        assert_eq!(messages(&storage), ["2", "3", "4"]);
+        assert_eq!(storage.enforce_retention().unwrap(), 0);
+        assert_eq!(storage.evicted(), 2);
+    }
+
+    #[test]
+    fn logs_older_than_the_age_are_evicted() {
+        let logs = [
+            log("old", Duration::hours(2)),
+            log("recent", Duration::minutes(1)),
+            log("new", Duration::zero()),
+        ];
+        let mut storage = storage(
+            RetentionPolicy {
+                max_age: Some(Duration::hours(1)),
+                ..RetentionPolicy::default()
+            },
+            &logs,
+        );
+
+        assert_eq!(storage.enforce_retention().unwrap(), 1);
+        assert_eq!(messages(&storage), ["recent", "new"]);
+    }
+
+    #[test]
+    fn oldest_logs_beyond_the_size_are_evicted() {
+        let message = "x".repeat(1000);
+        let logs: Vec<_> = (0..10).map(|_| log(&message, Duration::zero())).collect();
+        let mut storage = storage(
+            RetentionPolicy {
+                max_bytes: Some(4500),
+                ..RetentionPolicy::default()
+            },
+            &logs,
+        );
+
+        let evicted = storage.enforce_retention().unwrap();
+        assert!(evicted >= 6, "{evicted}");
+        assert!(storage.logs_amount().unwrap() <= 4);
+        assert!(storage.logs_amount().unwrap() > 0);
+    }
+
+    #[test]
+    fn unlimited_policy_keeps_everything() {
+        let logs = [
+            log("old", Duration::weeks(100)),
+            log("new", Duration::zero()),
+        ];
+        let mut storage = storage(RetentionPolicy::default(), &logs);
+
+        assert!(RetentionPolicy::default().is_unlimited());
+        assert_eq!(storage.enforce_retention().unwrap(), 0);
+        assert_eq!(storage.logs_amount().unwrap(), 2);
+    }
+}
@@ -0,0 +1,374 @@
+use rusqlite::{Connection, OptionalExtension, Result, params};
+
+use crate::storage::StorageError;
+
+/// A step that brings the schema from one version to the next. Databases created before
+/// versioning existed start at version 0 whatever they contain, so every step has to be
+/// safe to run on a database that already has its changes.
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Applied in order, the schema version is the number of applied migrations.
+/// Only ever append to this list.
+const MIGRATIONS: &[Migration] = &[
+    create_logs_table,
+    add_level_column,
+    create_var_table,
+    create_indexes,
+    create_search_index,
+    create_quarantine_table,
+    type_vars,
+    add_location_columns,
+    add_trace_columns,
+];
+
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+/// Applies the migrations the database is missing, each in its own transaction.
+/// Fails without touching the database if it was created by a newer heimdall.
+pub fn migrate(conn: &Connection) -> crate::storage::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)",
+        [],
+    )?;
+    let version: usize = conn
+        .query_row("SELECT version FROM schema_version", [], |row| row.get(0))
+        .optional()?
+        .unwrap_or(0);
+
+    if version > SCHEMA_VERSION {
+        return Err(StorageError::SchemaMismatch {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.unchecked_transaction()?;
+        migration(&tx)?;
+        tx.execute("DELETE FROM schema_version", [])?;
+        tx.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            params![index + 1],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn table_exists(conn: &Connection, name: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![name],
+        |row| row.get(0),
+    )
+}
+
+fn create_logs_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS logs (
+            id       INTEGER PRIMARY KEY,
+            ts       TEXT NOT NULL,
+            msg      TEXT NOT NULL,
+            ip       TEXT NOT NULL,
+            app      TEXT NOT NULL,
+            pid      INTEGER NOT NULL,
+            os       TEXT NOT NULL,
+            version  TEXT NOT NULL,
+            vars     TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_level_column(conn: &Connection) -> Result<()> {
+    let has_level: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('logs') WHERE name = 'level'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_level {
+        conn.execute(
+            "ALTER TABLE logs ADD COLUMN level INTEGER NOT NULL DEFAULT 2",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Vars are also stored one per row in `log_vars`, so that lookups by key and value are indexed
+fn create_var_table(conn: &Connection) -> Result<()> {
+    let exists = table_exists(conn, "log_vars")?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS log_vars (
+             log_id  INTEGER NOT NULL,
+             key     TEXT NOT NULL,
+             val     TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS log_vars_key_val ON log_vars (key, val);
+         CREATE INDEX IF NOT EXISTS log_vars_log_id ON log_vars (log_id);
+         CREATE TRIGGER IF NOT EXISTS log_vars_delete AFTER DELETE ON logs BEGIN
+             DELETE FROM log_vars WHERE log_id = old.id;
+         END;",
+    )?;
+    if !exists {
+        // Split the vars of logs stored before the table existed
+        conn.execute(
+            "INSERT INTO log_vars (log_id, key, val)
+             SELECT logs.id, vars.key, vars.value FROM logs, json_each(logs.vars) AS vars",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn create_indexes(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS logs_ts ON logs (julianday(ts));
+         CREATE INDEX IF NOT EXISTS logs_app ON logs (app);
+         CREATE INDEX IF NOT EXISTS logs_pid ON logs (pid);",
+    )
+}
+
+/// Keeps an FTS5 index over messages and vars in sync with the `logs` table
+fn create_search_index(conn: &Connection) -> Result<()> {
+    let exists = table_exists(conn, "logs_fts")?;
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS logs_fts
+             USING fts5(msg, vars, content = 'logs', content_rowid = 'id');
+         CREATE TRIGGER IF NOT EXISTS logs_fts_insert AFTER INSERT ON logs BEGIN
+             INSERT INTO logs_fts (rowid, msg, vars) VALUES (new.id, new.msg, new.vars);
+         END;
+         CREATE TRIGGER IF NOT EXISTS logs_fts_delete AFTER DELETE ON logs BEGIN
+             INSERT INTO logs_fts (logs_fts, rowid, msg, vars)
+                 VALUES ('delete', old.id, old.msg, old.vars);
+         END;",
+    )?;
+    if !exists {
+        // Index the logs that were stored before the index existed
+        conn.execute("INSERT INTO logs_fts (logs_fts) VALUES ('rebuild')", [])?;
+    }
+    Ok(())
+}
+
+fn create_quarantine_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quarantine (
+            id      INTEGER PRIMARY KEY,
+            ts      TEXT NOT NULL,
+            ip      TEXT NOT NULL,
+            reason  TEXT NOT NULL,
+            raw     BLOB NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Var values keep their type: `logs.var_types` maps the keys of non-string vars to their
+/// type, and `log_vars.val` is declared without a type so SQLite stores values natively
+fn type_vars(conn: &Connection) -> Result<()> {
+    let has_var_types: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('logs') WHERE name = 'var_types'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_var_types {
+        conn.execute(
+            "ALTER TABLE logs ADD COLUMN var_types TEXT NOT NULL DEFAULT '{}'",
+            [],
+        )?;
+    }
+
+    let has_kind: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('log_vars') WHERE name = 'kind'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_kind {
+        // Column types can't be changed in place, so the table is rebuilt. Everything
+        // stored so far was a string.
+        conn.execute_batch(
+            "DROP TRIGGER IF EXISTS log_vars_delete;
+             CREATE TABLE log_vars_typed (
+                 log_id  INTEGER NOT NULL,
+                 key     TEXT NOT NULL,
+                 kind    TEXT NOT NULL,
+                 val
+             );
+             INSERT INTO log_vars_typed (log_id, key, kind, val)
+                 SELECT log_id, key, 'string', val FROM log_vars;
+             DROP TABLE log_vars;
+             ALTER TABLE log_vars_typed RENAME TO log_vars;
+             CREATE INDEX log_vars_key_val ON log_vars (key, val);
+             CREATE INDEX log_vars_log_id ON log_vars (log_id);
+             CREATE TRIGGER log_vars_delete AFTER DELETE ON logs BEGIN
+                 DELETE FROM log_vars WHERE log_id = old.id;
+             END;",
+        )?;
+    }
+    Ok(())
+}
+
+/// Source locations, `NULL` for logs that were not sent with one
+fn add_location_columns(conn: &Connection) -> Result<()> {
+    let has_location: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('logs') WHERE name = 'file'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_location {
+        conn.execute_batch(
+            "ALTER TABLE logs ADD COLUMN file TEXT;
+             ALTER TABLE logs ADD COLUMN line INTEGER;
+             ALTER TABLE logs ADD COLUMN module TEXT;
+             ALTER TABLE logs ADD COLUMN crate_name TEXT;",
+        )?;
+    }
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS logs_file_line ON logs (file, line);
+         CREATE INDEX IF NOT EXISTS logs_module ON logs (module);",
+    )?;
+    Ok(())
+}
+
+/// Trace and span ids, `NULL` outside of a trace. `kind` is 0 for events and 1 for spans.
+fn add_trace_columns(conn: &Connection) -> Result<()> {
+    let has_trace: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('logs') WHERE name = 'trace_id'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_trace {
+        conn.execute_batch(
+            "ALTER TABLE logs ADD COLUMN trace_id TEXT;
+             ALTER TABLE logs ADD COLUMN span_id TEXT;
+             ALTER TABLE logs ADD COLUMN parent_span_id TEXT;
+             ALTER TABLE logs ADD COLUMN kind INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE logs ADD COLUMN duration_us INTEGER;",
+        )?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS logs_trace ON logs (trace_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(conn: &Connection) -> usize {
+        conn.query_row("SELECT version FROM schema_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn schema(conn: &Connection) -> Vec<(String, String)> {
+        let mut stmt = conn
+            .prepare("SELECT name, COALESCE(sql, '') FROM sqlite_master ORDER BY name")
+            .unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn fresh_database_is_migrated_to_the_latest_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+
+        assert_eq!(version(&conn), SCHEMA_VERSION);
+        for table in ["logs", "log_vars", "logs_fts", "quarantine"] {
+            assert!(table_exists(&conn, table).unwrap(), "{table} is missing");
+        }
+    }
+
+    #[test]
+    fn migrating_again_changes_nothing() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        let before = schema(&conn);
+
+        migrate(&conn).unwrap();
+        assert_eq!(version(&conn), SCHEMA_VERSION);
+        assert_eq!(schema(&conn), before);
+    }
+
+    #[test]
+    fn newer_database_is_refused() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute("UPDATE schema_version SET version = version + 1", [])
+            .unwrap();
+
+        assert!(matches!(
+            migrate(&conn),
+            Err(StorageError::SchemaMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rows_of_an_unversioned_database_survive() {
+        // The schema heimdall used before migrations existed
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE logs (
+                id       INTEGER PRIMARY KEY,
+                ts       TEXT NOT NULL,
+                msg      TEXT NOT NULL,
+                ip       TEXT NOT NULL,
+                app      TEXT NOT NULL,
+                pid      INTEGER NOT NULL,
+                os       TEXT NOT NULL,
+                version  TEXT NOT NULL,
+                vars     TEXT NOT NULL
+            );
+            INSERT INTO logs (ts, msg, ip, app, pid, os, version, vars) VALUES
+                ('2024-05-01T12:00:00+00:00', 'first', '10.0.0.1', 'app', 1, 'linux', '1.0',
+                 '{\"user\":\"alice\"}'),
+                ('2024-05-01T12:00:01+00:00', 'second', '10.0.0.1', 'app', 1, 'linux', '1.0',
+                 '{}');",
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(version(&conn), SCHEMA_VERSION);
+
+        let rows: Vec<(String, i64, i64, Option<String>)> = conn
+            .prepare("SELECT msg, level, kind, trace_id FROM logs ORDER BY id")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            [
+                ("first".to_string(), 2, 0, None),
+                ("second".to_string(), 2, 0, None)
+            ]
+        );
+
+        let var: (i64, String, String) = conn
+            .query_row(
+                "SELECT log_id, kind, val FROM log_vars WHERE key = 'user'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(var, (1, "string".to_string(), "alice".to_string()));
+
+        let found: i64 = conn
+            .query_row(
+                "SELECT rowid FROM logs_fts WHERE logs_fts MATCH 'second'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(found, 2);
+    }
+}
@@ -0,0 +1,733 @@
+mod migrations;
+
+use chrono::{DateTime, FixedOffset};
+use regex::Regex;
+use rusqlite::{
+    Connection, Error, OptionalExtension, Row, ToSql,
+    functions::FunctionFlags,
+    params, params_from_iter,
+    types::{Type, Value as SqlValue},
+};
+use serde_json::Value as JsonValue;
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::{
+    LogQuery, LogStore, MessageFilter, QUARANTINE_CAPACITY, QuarantinedMessage, Result, VarOp,
+};
+use crate::prelude::{RsContext, RsKind, RsLevel, RsLocation, RsLog, RsTrace, RsValue, RsVar};
+
+/// What a crash may cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Durability {
+    /// Logs are written in batches, a crash loses the batch that was not flushed yet
+    #[default]
+    Batched,
+    /// Every log is committed and synced to disk before `add_log` returns
+    Immediate,
+}
+
+impl FromStr for Durability {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "batched" => Ok(Durability::Batched),
+            "immediate" => Ok(Durability::Immediate),
+            _ => Err(format!(
+                "Unknown durability '{s}', expected batched or immediate"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SqliteOptions {
+    /// A batch is flushed once it holds this many logs
+    pub batch_size: usize,
+    /// A batch is flushed once its oldest log waited this long
+    pub flush_interval: Duration,
+    pub durability: Durability,
+}
+
+impl Default for SqliteOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            flush_interval: Duration::from_millis(50),
+            durability: Durability::Batched,
+        }
+    }
+}
+
+/// Stores logs in the `logs` table of a SQLite database.
+/// Logs are buffered and written in transactions, reads only see flushed logs.
+pub struct SqliteStore {
+    conn: Connection,
+    options: SqliteOptions,
+    pending: Vec<(usize, RsLog)>,
+    pending_since: Option<Instant>,
+    next_id: usize,
+    skipped_rows: Cell<usize>,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl Into<String>) -> Result<Self> {
+        Self::open_with(path, SqliteOptions::default())
+    }
+
+    pub fn open_with(path: impl Into<String>, options: SqliteOptions) -> Result<Self> {
+        let path_str = path.into();
+        let conn = Connection::open(&path_str)?;
+        // WAL lets readers run alongside a batch being written, and only needs a full
+        // sync at checkpoints unless every commit has to be durable
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(
+            None,
+            "synchronous",
+            match options.durability {
+                Durability::Batched => "NORMAL",
+                Durability::Immediate => "FULL",
+            },
+        )?;
+        migrations::migrate(&conn)?;
+        register_regexp(&conn)?;
+        // Ids are assigned when a log is buffered, before it reaches the table
+        let next_id: usize =
+            conn.query_row("SELECT COALESCE(MAX(id), 0) + 1 FROM logs", [], |row| {
+                row.get(0)
+            })?;
+        Ok(Self {
+            conn,
+            options,
+            pending: Vec::new(),
+            pending_since: None,
+            next_id,
+            skipped_rows: Cell::new(0),
+        })
+    }
+
+    /// Writes the buffered logs in one transaction. They stay buffered if it fails.
+    fn write_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO logs (id, ts, msg, ip, app, pid, os, version, vars, var_types, level,
+                                   file, line, module, crate_name,
+                                   trace_id, span_id, parent_span_id, kind, duration_us)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                         ?16, ?17, ?18, ?19, ?20)",
+            )?;
+            let mut var_stmt = tx.prepare_cached(
+                "INSERT INTO log_vars (log_id, key, kind, val) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (id, log) in &self.pending {
+                let (vars_json, var_types) = vars_to_json(&log.vars);
+                let location = log.location.as_ref();
+                let trace = log.trace.as_ref();
+
+                stmt.execute(params![
+                    *id as i64,
+                    log.ts.to_rfc3339(),
+                    log.msg,
+                    log.ip,
+                    log.context.app,
+                    log.context.pid,
+                    log.context.os,
+                    log.context.version,
+                    vars_json,
+                    var_types,
+                    log.level.as_i64(),
+                    location.map(|location| &location.file),
+                    location.map(|location| location.line),
+                    location.map(|location| &location.module),
+                    location.map(|location| &location.crate_name),
+                    trace.map(|trace| &trace.trace_id),
+                    trace.map(|trace| &trace.span_id),
+                    trace.and_then(|trace| trace.parent_span_id.as_ref()),
+                    kind_to_i64(log.kind),
+                    log.kind
+                        .duration()
+                        .map(|duration| duration.as_micros() as i64)
+                ])?;
+                for var in &log.vars {
+                    var_stmt.execute(params![
+                        *id as i64,
+                        var.key,
+                        var.val.type_name(),
+                        value_to_sql(&var.val)
+                    ])?;
+                }
+            }
+        }
+        tx.commit()?;
+
+        self.pending.clear();
+        self.pending_since = None;
+        Ok(())
+    }
+
+    /// Collects decoded rows, skipping and counting the ones that could not be decoded
+    fn collect_logs(
+        &self,
+        rows: impl Iterator<Item = rusqlite::Result<Option<(usize, RsLog)>>>,
+    ) -> Result<Vec<(usize, RsLog)>> {
+        let mut logs = Vec::new();
+        for row in rows {
+            match row? {
+                Some(log) => logs.push(log),
+                None => self.skipped_rows.set(self.skipped_rows.get() + 1),
+            }
+        }
+        Ok(logs)
+    }
+
+    fn single_log(&self, row: Option<Option<(usize, RsLog)>>) -> Option<RsLog> {
+        match row {
+            Some(Some((_, log))) => Some(log),
+            Some(None) => {
+                self.skipped_rows.set(self.skipped_rows.get() + 1);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+impl Drop for SqliteStore {
+    fn drop(&mut self) {
+        if let Err(e) = self.write_pending() {
+            eprintln!("Failed to flush {} logs to SQLite: {e}", self.pending.len());
+        }
+    }
+}
+
+/// Deletes only add tombstones to the FTS5 index, merging its segments drops them
+/// so that evictions actually shrink the database
+fn merge_search_index(conn: &Connection, evicted: usize) -> rusqlite::Result<()> {
+    if evicted > 0 {
+        conn.execute(
+            "INSERT INTO logs_fts (logs_fts, rank) VALUES ('merge', -16)",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// SQLite has the `REGEXP` operator but no implementation behind it
+fn register_regexp(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            // The pattern is the same for every row, so compile it once per statement
+            let regex: Arc<Regex> = ctx.get_or_create_aux(0, |pattern| {
+                Regex::new(pattern.as_str()?).map_err(|e| Error::UserFunctionError(e.into()))
+            })?;
+            let text = ctx
+                .get_raw(1)
+                .as_str()
+                .map_err(|e| Error::UserFunctionError(e.into()))?;
+            Ok(regex.is_match(text))
+        },
+    )
+}
+
+impl LogStore for SqliteStore {
+    fn add_log(&mut self, log: RsLog) -> Result<usize> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.push((id, log));
+        self.pending_since.get_or_insert_with(Instant::now);
+
+        if self.options.durability == Durability::Immediate
+            || self.pending.len() >= self.options.batch_size
+        {
+            self.write_pending()?;
+        } else {
+            self.flush_if_due()?;
+        }
+        Ok(id)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.write_pending()
+    }
+
+    fn flush_if_due(&mut self) -> Result<()> {
+        match self.pending_since {
+            Some(since) if since.elapsed() >= self.options.flush_interval => self.write_pending(),
+            _ => Ok(()),
+        }
+    }
+
+    fn logs_amount(&self) -> Result<usize> {
+        let mut stmt = self.conn.prepare_cached("SELECT COUNT(*) FROM logs")?;
+        Ok(stmt.query_row([], |row| row.get(0))?)
+    }
+
+    fn get_log(&self, index: usize) -> Result<Option<RsLog>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, ts, msg, ip, app, pid, os, version, vars, level, var_types,
+                    file, line, module, crate_name,
+                    trace_id, span_id, parent_span_id, kind, duration_us
+             FROM logs
+             ORDER BY id DESC
+             LIMIT 1 OFFSET ?1",
+        )?;
+        let row = stmt
+            .query_row(params![index as i64], log_from_row)
+            .optional()?;
+        Ok(self.single_log(row))
+    }
+
+    fn get_log_by_id(&self, id: usize) -> Result<Option<RsLog>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, ts, msg, ip, app, pid, os, version, vars, level, var_types,
+                    file, line, module, crate_name,
+                    trace_id, span_id, parent_span_id, kind, duration_us
+             FROM logs
+             WHERE id = ?1",
+        )?;
+        let row = stmt
+            .query_row(params![id as i64], log_from_row)
+            .optional()?;
+        Ok(self.single_log(row))
+    }
+
+    fn get_visible_logs(&self, start: usize, amount: usize) -> Result<Vec<(usize, RsLog)>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, ts, msg, ip, app, pid, os, version, vars, level, var_types,
+                    file, line, module, crate_name,
+                    trace_id, span_id, parent_span_id, kind, duration_us
+             FROM logs
+             ORDER BY id DESC
+             LIMIT ?1 OFFSET ?2",
+        )?;
+
+        let rows = stmt.query_map(params![amount as i64, start as i64], log_from_row)?;
+        self.collect_logs(rows)
+    }
+
+    fn search(&self, query: &str, offset: usize, limit: usize) -> Result<Vec<usize>> {
+        // Every term is quoted so that user input never trips over the FTS5 query syntax
+        let fts_query = query
+            .split_whitespace()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if fts_query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT rowid
+             FROM logs_fts
+             WHERE logs_fts MATCH ?1
+             ORDER BY rank
+             LIMIT ?2 OFFSET ?3",
+        )?;
+        let rows = stmt.query_map(params![fts_query, limit as i64, offset as i64], |row| {
+            row.get(0)
+        })?;
+
+        let mut ids = Vec::new();
+        for id in rows {
+            ids.push(id?);
+        }
+        Ok(ids)
+    }
+
+    fn count(&self, query: &LogQuery) -> Result<usize> {
+        let (where_clause, params) = where_clause(query);
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT COUNT(*) FROM logs {where_clause}"))?;
+        Ok(stmt.query_row(params_from_iter(params.iter()), |row| row.get(0))?)
+    }
+
+    fn query(&self, query: &LogQuery, offset: usize, limit: usize) -> Result<Vec<(usize, RsLog)>> {
+        let (where_clause, mut params) = where_clause(query);
+        params.push(Box::new(limit as i64));
+        params.push(Box::new(offset as i64));
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, ts, msg, ip, app, pid, os, version, vars, level, var_types,
+                    file, line, module, crate_name,
+                    trace_id, span_id, parent_span_id, kind, duration_us
+             FROM logs
+             {where_clause}
+             ORDER BY id DESC
+             LIMIT ? OFFSET ?"
+        ))?;
+
+        let rows = stmt.query_map(params_from_iter(params.iter()), log_from_row)?;
+        self.collect_logs(rows)
+    }
+
+    fn evict_oldest(&mut self, amount: usize) -> Result<usize> {
+        self.write_pending()?;
+        let evicted = self.conn.execute(
+            "DELETE FROM logs WHERE id IN (SELECT id FROM logs ORDER BY id LIMIT ?1)",
+            params![amount as i64],
+        )?;
+        merge_search_index(&self.conn, evicted)?;
+        Ok(evicted)
+    }
+
+    fn evict_before(&mut self, ts: DateTime<FixedOffset>) -> Result<usize> {
+        self.write_pending()?;
+        let evicted = self.conn.execute(
+            "DELETE FROM logs WHERE julianday(ts) < julianday(?1)",
+            params![ts.to_rfc3339()],
+        )?;
+        merge_search_index(&self.conn, evicted)?;
+        Ok(evicted)
+    }
+
+    fn size_bytes(&self) -> Result<u64> {
+        // Pages on the freelist are reused by new inserts, so they don't count. The quarantine
+        // is bounded on its own and evicting logs can't shrink it, so its rows are left out.
+        // The search index and `log_vars` stay in as they shrink along with the logs.
+        let used: u64 = self.conn.query_row(
+            "SELECT (page_count - freelist_count) * page_size
+             FROM pragma_page_count(), pragma_freelist_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        let quarantine: u64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(length(ts) + length(ip) + length(reason) + length(raw)), 0)
+             FROM quarantine",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(used.saturating_sub(quarantine))
+    }
+
+    fn vacuum(&mut self) -> Result<()> {
+        self.write_pending()?;
+        self.conn
+            .execute("INSERT INTO logs_fts (logs_fts) VALUES ('optimize')", [])?;
+        Ok(self.conn.execute_batch("VACUUM")?)
+    }
+
+    fn quarantine(&mut self, message: QuarantinedMessage) -> Result<()> {
+        self.conn
+            .prepare_cached("INSERT INTO quarantine (ts, ip, reason, raw) VALUES (?1, ?2, ?3, ?4)")?
+            .execute(params![
+                message.ts.to_rfc3339(),
+                message.ip,
+                message.reason,
+                message.raw
+            ])?;
+        self.conn
+            .prepare_cached(
+                "DELETE FROM quarantine WHERE id <= (SELECT MAX(id) FROM quarantine) - ?1",
+            )?
+            .execute(params![QUARANTINE_CAPACITY as i64])?;
+        Ok(())
+    }
+
+    fn quarantined(&self, offset: usize, limit: usize) -> Result<Vec<QuarantinedMessage>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT ts, ip, reason, raw FROM quarantine ORDER BY id DESC LIMIT ?1 OFFSET ?2",
+        )?;
+        let rows = stmt.query_map(params![limit as i64, offset as i64], |row| {
+            let ts: String = row.get(0)?;
+            Ok(QuarantinedMessage {
+                ts: DateTime::parse_from_rfc3339(&ts)
+                    .map_err(|e| Error::FromSqlConversionFailure(0, Type::Text, e.into()))?,
+                ip: row.get(1)?,
+                reason: row.get(2)?,
+                raw: row.get(3)?,
+            })
+        })?;
+
+        let mut messages = Vec::new();
+        for message in rows {
+            messages.push(message?);
+        }
+        Ok(messages)
+    }
+
+    fn skipped_rows(&self) -> usize {
+        self.skipped_rows.get()
+    }
+}
+
+/// Builds a `WHERE` clause (empty if there is nothing to filter) and its parameters
+fn where_clause(query: &LogQuery) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut clauses: Vec<String> = Vec::new();
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+    // The time bounds compare `julianday(ts)` so that the `logs_ts` index applies
+    if let Some(from) = query.from {
+        clauses.push("julianday(ts) >= julianday(?)".into());
+        params.push(Box::new(from.to_rfc3339()));
+    }
+    if let Some(to) = query.to {
+        clauses.push("julianday(ts) < julianday(?)".into());
+        params.push(Box::new(to.to_rfc3339()));
+    }
+    if let Some(app) = &query.app {
+        clauses.push("app = ?".into());
+        params.push(Box::new(app.clone()));
+    }
+    if let Some(version) = &query.version {
+        clauses.push("version = ?".into());
+        params.push(Box::new(version.clone()));
+    }
+    if let Some(pid) = query.pid {
+        clauses.push("pid = ?".into());
+        params.push(Box::new(pid));
+    }
+    if let Some(ip) = &query.ip {
+        clauses.push("ip = ?".into());
+        params.push(Box::new(ip.clone()));
+    }
+    if let Some(level) = query.level {
+        clauses.push("level >= ?".into());
+        params.push(Box::new(level.as_i64()));
+    }
+    match &query.msg {
+        Some(MessageFilter::Contains(needle)) => {
+            clauses.push("instr(msg, ?) > 0".into());
+            params.push(Box::new(needle.clone()));
+        }
+        Some(MessageFilter::Regex(regex)) => {
+            clauses.push("msg REGEXP ?".into());
+            params.push(Box::new(regex.as_str().to_string()));
+        }
+        None => {}
+    }
+    for filter in &query.vars {
+        clauses.push(var_clause(filter.op, &filter.value));
+        params.push(Box::new(filter.key.clone()));
+        params.push(Box::new(value_to_sql(&filter.value)));
+        if filter.op == VarOp::Eq {
+            params.push(Box::new(filter.value.to_string()));
+        }
+    }
+
+    if let Some(file) = &query.file {
+        clauses.push("file = ?".into());
+        params.push(Box::new(file.clone()));
+    }
+    if let Some(line) = query.line {
+        clauses.push("line = ?".into());
+        params.push(Box::new(line));
+    }
+    if let Some(module) = &query.module {
+        // Module paths never contain glob characters
+        clauses.push("(module = ? OR module GLOB ?)".into());
+        params.push(Box::new(module.clone()));
+        params.push(Box::new(format!("{module}::*")));
+    }
+    if let Some(crate_name) = &query.crate_name {
+        clauses.push("crate_name = ?".into());
+        params.push(Box::new(crate_name.clone()));
+    }
+
+    if let Some(trace_id) = &query.trace_id {
+        clauses.push("trace_id = ?".into());
+        params.push(Box::new(trace_id.clone()));
+    }
+
+    if clauses.is_empty() {
+        (String::new(), params)
+    } else {
+        (format!("WHERE {}", clauses.join(" AND ")), params)
+    }
+}
+
+/// Decodes a row, `None` if its contents are damaged
+fn log_from_row(row: &Row<'_>) -> rusqlite::Result<Option<(usize, RsLog)>> {
+    match decode_row(row) {
+        Ok(log) => Ok(Some(log)),
+        Err(
+            Error::InvalidColumnType(..)
+            | Error::FromSqlConversionFailure(..)
+            | Error::IntegralValueOutOfRange(..),
+        ) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn decode_row(row: &Row<'_>) -> rusqlite::Result<(usize, RsLog)> {
+    let id: usize = row.get(0)?;
+    let ts_str: String = row.get(1)?;
+    let ts = DateTime::parse_from_rfc3339(&ts_str)
+        .map_err(|e| Error::FromSqlConversionFailure(1, Type::Text, e.into()))?;
+
+    let vars_str: String = row.get(8)?;
+    let vars_json: serde_json::Map<String, JsonValue> = serde_json::from_str(&vars_str)
+        .map_err(|e| Error::FromSqlConversionFailure(8, Type::Text, e.into()))?;
+    let types_str: String = row.get(10)?;
+    let var_types: HashMap<String, String> = serde_json::from_str(&types_str)
+        .map_err(|e| Error::FromSqlConversionFailure(10, Type::Text, e.into()))?;
+    let vars = vars_json
+        .into_iter()
+        .map(|(key, val)| {
+            let kind = var_types.get(&key).map(String::as_str).unwrap_or("string");
+            let val = value_from_json(kind, val).ok_or_else(|| {
+                Error::FromSqlConversionFailure(
+                    8,
+                    Type::Text,
+                    format!("var {key:?} is not a valid {kind}").into(),
+                )
+            })?;
+            Ok(RsVar { key, val })
+        })
+        .collect::<rusqlite::Result<_>>()?;
+
+    let level: i64 = row.get(9)?;
+    let file: Option<String> = row.get(11)?;
+    let location = match file {
+        Some(file) => Some(RsLocation {
+            file,
+            line: row.get(12)?,
+            module: row.get(13)?,
+            crate_name: row.get(14)?,
+        }),
+        None => None,
+    };
+    let trace_id: Option<String> = row.get(15)?;
+    let trace = match trace_id {
+        Some(trace_id) => Some(RsTrace {
+            trace_id,
+            span_id: row.get(16)?,
+            parent_span_id: row.get(17)?,
+        }),
+        None => None,
+    };
+    let kind = match (row.get::<_, i64>(18)?, row.get::<_, Option<i64>>(19)?) {
+        (1, Some(duration_us)) => RsKind::Span {
+            duration: Duration::from_micros(duration_us as u64),
+        },
+        _ => RsKind::Event,
+    };
+
+    Ok((
+        id,
+        RsLog {
+            ts,
+            level: RsLevel::from_i64(level).unwrap_or_default(),
+            msg: row.get(2)?,
+            ip: row.get(3)?,
+            context: RsContext {
+                app: row.get(4)?,
+                pid: row.get(5)?,
+                os: row.get(6)?,
+                version: row.get(7)?,
+            },
+            vars,
+            location,
+            trace,
+            kind,
+        },
+    ))
+}
+
+fn kind_to_i64(kind: RsKind) -> i64 {
+    match kind {
+        RsKind::Event => 0,
+        RsKind::Span { .. } => 1,
+    }
+}
+
+/// Converts vars to the `vars` JSON column and the `var_types` column, which only lists the
+/// vars that aren't strings
+fn vars_to_json(vars: &[RsVar]) -> (String, String) {
+    let mut values = serde_json::Map::new();
+    let mut types = serde_json::Map::new();
+    for var in vars {
+        let value = match &var.val {
+            RsValue::Int(value) => JsonValue::from(*value),
+            RsValue::Float(value) => JsonValue::from(*value),
+            RsValue::Bool(value) => JsonValue::from(*value),
+            value => JsonValue::String(value.to_string()),
+        };
+        values.insert(var.key.clone(), value);
+        if !matches!(var.val, RsValue::Str(_)) {
+            types.insert(var.key.clone(), var.val.type_name().into());
+        }
+    }
+    (
+        JsonValue::Object(values).to_string(),
+        JsonValue::Object(types).to_string(),
+    )
+}
+
+/// Reverses `vars_to_json` for one var, `None` if the value doesn't fit its type
+fn value_from_json(kind: &str, value: JsonValue) -> Option<RsValue> {
+    match (kind, value) {
+        ("int", value) => value.as_i64().map(RsValue::Int),
+        // Non-finite floats are written as `null`
+        ("float", JsonValue::Null) => Some(RsValue::Float(f64::NAN)),
+        ("float", value) => value.as_f64().map(RsValue::Float),
+        ("bool", value) => value.as_bool().map(RsValue::Bool),
+        ("bytes", JsonValue::String(hex)) => bytes_from_hex(&hex).map(RsValue::Bytes),
+        ("timestamp", JsonValue::String(ts)) => DateTime::parse_from_rfc3339(&ts)
+            .ok()
+            .map(RsValue::Timestamp),
+        ("string", JsonValue::String(value)) => Some(RsValue::Str(value)),
+        _ => None,
+    }
+}
+
+fn bytes_from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// The native SQLite value a var is stored as in `log_vars`
+fn value_to_sql(value: &RsValue) -> SqlValue {
+    match value {
+        RsValue::Int(value) => SqlValue::Integer(*value),
+        RsValue::Float(value) => SqlValue::Real(*value),
+        RsValue::Bool(value) => SqlValue::Integer(*value as i64),
+        RsValue::Str(value) => SqlValue::Text(value.clone()),
+        RsValue::Bytes(value) => SqlValue::Blob(value.clone()),
+        RsValue::Timestamp(_) => SqlValue::Text(value.to_string()),
+    }
+}
+
+/// The condition for one var filter, taking the key and the value (and for `Eq` also the value
+/// as text, so that vars sent as strings still match) as parameters. Orderings only compare
+/// vars of a compatible type, like `VarFilter::matches`.
+fn var_clause(op: VarOp, value: &RsValue) -> String {
+    let op = match op {
+        VarOp::Eq => {
+            return "id IN (SELECT log_id FROM log_vars WHERE key = ? AND (val = ? OR val = ?))"
+                .into();
+        }
+        VarOp::Lt => "<",
+        VarOp::Le => "<=",
+        VarOp::Gt => ">",
+        VarOp::Ge => ">=",
+    };
+    let condition = match value {
+        RsValue::Int(_) | RsValue::Float(_) => format!("kind IN ('int', 'float') AND val {op} ?"),
+        RsValue::Timestamp(_) => {
+            format!("kind = 'timestamp' AND julianday(val) {op} julianday(?)")
+        }
+        RsValue::Str(_) => format!("kind = 'string' AND val {op} ?"),
+        // Booleans and bytes have no order
+        RsValue::Bool(_) | RsValue::Bytes(_) => format!("0 AND val {op} ?"),
+    };
+    format!("id IN (SELECT log_id FROM log_vars WHERE key = ? AND {condition})")
+}
@@ -101,8 +101,9 @@ impl App {
             // SAFETY: we know popup_ptr points to a valid object
             if let Some(popup_ptr) = popup_ptr {
                 let mut app_data = self.app_data.borrow_mut(); // re-borrow after releasing first borrow
-                unsafe {
-                    (*popup_ptr).update(&mut app_data);
+                let finished = unsafe { (*popup_ptr).update(&mut app_data) };
+                if finished {
+                    app_data.popups.remove(0);
                 }
             }
         }
@@ -115,22 +116,28 @@ impl App {
     fn pool_events(&mut self) -> Result<()> {
         if event::poll(Duration::from_millis(250)).context("Failed to poll event")? {
             if let event::Event::Key(key) = event::read().context("Failed to read event")? {
-                match (key.modifiers, key.code) {
-                    (KeyModifiers::NONE, KeyCode::Char('q')) => {
-                        self.app_data
-                            .borrow_mut()
-                            .popups
-                            .push(Box::new(ExitPopup::new()));
-                    }
-                    _ => {}
-                }
-
                 let mut app_data = self.app_data.borrow_mut();
 
                 if let Some(popup) = app_data.popups.get_mut(0) {
                     popup.on_event(key);
                 } else {
                     match (key.modifiers, key.code) {
+                        (KeyModifiers::NONE, KeyCode::Char('q')) => {
+                            app_data.popups.push(Box::new(ExitPopup::new()));
+                        }
+                        (KeyModifiers::NONE, KeyCode::Char('/')) => {
+                            app_data.popups.push(Box::new(SearchPopup::new()));
+                        }
+                        (KeyModifiers::NONE, KeyCode::Char('t')) => {
+                            if let Some(trace_id) = selected_trace_id(&app_data) {
+                                app_data.popups.push(Box::new(TracePopup::new(trace_id)));
+                            }
+                        }
+                        (KeyModifiers::SHIFT, KeyCode::Char('T')) => {
+                            if let Some(trace_id) = selected_trace_id(&app_data) {
+                                app_data.popups.push(Box::new(WaterfallPopup::new(trace_id)));
+                            }
+                        }
                         (KeyModifiers::NONE, KeyCode::Char('w')) => {
                             app_data.should_exit = true;
                             return Ok(());
@@ -164,6 +171,20 @@ impl App {
     }
 }
 
+/// Trace of the log selected in the logs panel, if it is part of one
+fn selected_trace_id(app_data: &AppData) -> Option<String> {
+    let (id, _) = app_data.logs_panel.selected_log()?;
+    let log = app_data
+        .data
+        .lock()
+        .unwrap()
+        .storage
+        .get_log_by_id(*id)
+        .ok()
+        .flatten()?;
+    Some(log.trace?.trace_id)
+}
+
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let [statuses, data] =
@@ -172,14 +193,15 @@ impl Widget for &App {
         let [status, threads] =
             Layout::horizontal([Constraint::Fill(1), Constraint::Length(30)]).areas(statuses);
 
-        let log = self.app_data.borrow().data.lock().unwrap().storage.get_log(
-            self.app_data
-                .borrow()
-                .logs_panel
-                .logs_state
-                .selected()
-                .unwrap_or(0),
-        );
+        // Read errors are shown by the logs panel, the info panel is just left out
+        let log = {
+            let app_data = self.app_data.borrow();
+            let id = app_data.logs_panel.selected_log().map(|(id, _)| *id);
+            id.and_then(|id| {
+                let data = app_data.data.lock().unwrap();
+                data.storage.get_log_by_id(id).ok().flatten()
+            })
+        };
 
         let [logs, info] = if log.is_some() {
             Layout::horizontal([Constraint::Fill(2), Constraint::Fill(1)])
@@ -188,7 +210,22 @@ impl Widget for &App {
         }
         .areas(data);
 
-        StatusPanel::from(self.app_data.borrow().logs_panel.logs_amount).render(status, buf);
+        let (evicted, skipped, rejected) = {
+            let app_data = self.app_data.borrow();
+            let data = app_data.data.lock().unwrap();
+            (
+                data.storage.evicted(),
+                data.storage.skipped_rows(),
+                data.storage.rejected().values().sum(),
+            )
+        };
+        StatusPanel::from(
+            self.app_data.borrow().logs_panel.logs_amount,
+            evicted,
+            skipped,
+            rejected,
+        )
+        .render(status, buf);
         ThreadsPanel::from(self.app_data.borrow().data.clone()).render(threads, buf);
         self.app_data.borrow().logs_panel.render(logs, buf);
 
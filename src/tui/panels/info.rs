@@ -6,7 +6,7 @@ use ratatui::{
     widgets::{Block, BorderType, Paragraph, Widget, Wrap},
 };
 
-use super::Panel;
+use super::{Panel, level_color, value_color};
 use heimdall::log::RsLog;
 
 pub struct InfoPanel {
@@ -27,6 +27,15 @@ impl Panel for InfoPanel {
             .border_type(BorderType::Rounded);
 
         let mut lines = vec![
+            Line::from(vec![
+                Span::styled("level ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    self.log.level.as_str().to_uppercase(),
+                    Style::default()
+                        .fg(level_color(self.log.level))
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ]),
             Line::from(vec![
                 Span::styled("at ", Style::default().fg(Color::DarkGray)),
                 Span::styled(
@@ -68,9 +77,43 @@ impl Panel for InfoPanel {
                         .add_modifier(Modifier::BOLD),
                 ),
             ]),
-            Line::from(""),
-            Line::from(self.log.msg.to_string()),
         ];
+        if let Some(location) = &self.log.location {
+            lines.push(Line::from(vec![
+                Span::styled("in ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("{} ", location.module),
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(location.to_string(), Style::default().fg(Color::Blue)),
+            ]));
+        }
+        if let Some(trace) = &self.log.trace {
+            lines.push(Line::from(vec![
+                Span::styled("trace ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("{} ", trace.trace_id),
+                    Style::default().fg(Color::Magenta),
+                ),
+                Span::styled("span ", Style::default().fg(Color::DarkGray)),
+                Span::styled(trace.span_id.as_str(), Style::default().fg(Color::Magenta)),
+            ]));
+        }
+        if let Some(duration) = self.log.kind.duration() {
+            lines.push(Line::from(vec![
+                Span::styled("took ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("{:.3}ms", duration.as_secs_f64() * 1000.0),
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ]));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(self.log.msg.to_string()));
         if !self.log.vars.is_empty() {
             lines.push(Line::from(""));
             lines.push(Line::from(vec![Span::styled(
@@ -84,7 +127,14 @@ impl Panel for InfoPanel {
                         format!("{}: ", var.key),
                         Style::default().fg(Color::DarkGray),
                     ),
-                    Span::styled(var.val.to_string(), Style::default().fg(Color::Green)),
+                    Span::styled(
+                        var.val.to_string(),
+                        Style::default().fg(value_color(&var.val)),
+                    ),
+                    Span::styled(
+                        format!(" ({})", var.val.type_name()),
+                        Style::default().fg(Color::DarkGray),
+                    ),
                 ])
             }));
         }
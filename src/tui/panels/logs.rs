@@ -7,10 +7,13 @@ use ratatui::{
 };
 use std::sync::{Arc, Mutex};
 
-use super::Panel;
+use super::{Panel, level_color, value_color};
 use crate::data::Data;
 use heimdall::log::RsLog;
 
+/// Logs are fetched in chunks of this many around the scroll position
+const CHUNK_SIZE: usize = 64;
+
 pub struct LogsPanel {
     pub data: Arc<Mutex<Data>>,
     pub area_height: Arc<Mutex<usize>>,
@@ -19,6 +22,8 @@ pub struct LogsPanel {
     pub logs_amount: usize,
     pub visible_logs: Vec<(usize, RsLog)>,
     pub updated: bool,
+    /// The last storage error, shown until a read succeeds again
+    pub error: Option<String>,
 }
 
 impl LogsPanel {
@@ -31,13 +36,20 @@ impl LogsPanel {
             logs_amount: 0,
             visible_logs: vec![],
             updated: false,
+            error: None,
         }
     }
 
     pub fn update(&mut self) {
         let data = self.data.lock().unwrap();
 
-        let total_logs = data.storage.logs_amount();
+        let total_logs = match data.storage.logs_amount() {
+            Ok(total_logs) => total_logs,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
+            }
+        };
         if total_logs == 0 {
             self.logs_state.select(None);
             self.visible_logs.clear();
@@ -62,21 +74,37 @@ impl LogsPanel {
                 .min(total_logs.saturating_sub(visible_height));
         }
 
-        let chunk_size = 64;
         let area_height = *self.area_height.lock().unwrap();
-        let chunk_start = (self.logs_scroll / chunk_size) * chunk_size;
+        let chunk_start = (self.logs_scroll / CHUNK_SIZE) * CHUNK_SIZE;
 
-        self.visible_logs = data
+        match data
             .storage
-            .get_visible_logs(chunk_start, chunk_size + area_height)
-            .unwrap_or_default();
+            .get_visible_logs(chunk_start, CHUNK_SIZE + area_height)
+        {
+            Ok(visible_logs) => {
+                self.visible_logs = visible_logs;
+                self.error = None;
+            }
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
+            }
+        }
 
         self.logs_amount = total_logs;
         self.updated = false;
     }
 
+    /// The highlighted log as of the last update. Backends don't agree on how `get_log` indexes,
+    /// so this is what lookups of the selected log should go through.
+    pub fn selected_log(&self) -> Option<&(usize, RsLog)> {
+        let chunk_start = (self.logs_scroll / CHUNK_SIZE) * CHUNK_SIZE;
+        let selected = self.logs_state.selected()?;
+        self.visible_logs.get(selected.checked_sub(chunk_start)?)
+    }
+
     fn get_visible_logs(&self) -> Vec<Line<'_>> {
-        let scroll_offset_in_chunk = self.logs_scroll % 64;
+        let scroll_offset_in_chunk = self.logs_scroll % CHUNK_SIZE;
         let visible_height = *self.area_height.lock().unwrap();
 
         let logs_slice = &self.visible_logs[scroll_offset_in_chunk
@@ -102,6 +130,13 @@ impl LogsPanel {
                             .fg(Color::Blue)
                             .add_modifier(Modifier::BOLD),
                     ),
+                    Span::raw(" "),
+                    Span::styled(
+                        format!("{:>5}", log.1.level.as_str().to_uppercase()),
+                        Style::default()
+                            .fg(level_color(log.1.level))
+                            .add_modifier(Modifier::BOLD),
+                    ),
                     Span::styled(": ", Style::default().fg(Color::DarkGray)),
                     Span::raw(format!("{}", log.1.msg)),
                 ];
@@ -110,7 +145,10 @@ impl LogsPanel {
                     spans.push(Span::raw(" "));
                     spans.push(Span::styled(&var.key, Style::default().fg(Color::Green)));
                     spans.push(Span::styled("=", Style::default().fg(Color::DarkGray)));
-                    spans.push(Span::styled(&var.val, Style::default().fg(Color::Yellow)));
+                    spans.push(Span::styled(
+                        var.val.to_string(),
+                        Style::default().fg(value_color(&var.val)),
+                    ));
                 }
 
                 Line::from(spans)
@@ -121,10 +159,16 @@ impl LogsPanel {
 
 impl Panel for LogsPanel {
     fn render(&self, area: Rect, buf: &mut Buffer) {
-        let block = Block::bordered()
+        let mut block = Block::bordered()
             .title("Logs")
             .title_alignment(Alignment::Center)
             .border_type(BorderType::Rounded);
+        if let Some(error) = &self.error {
+            block = block.title_bottom(Line::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            ));
+        }
 
         *self
             .area_height
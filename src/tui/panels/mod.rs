@@ -3,12 +3,34 @@ mod logs;
 mod status;
 mod threads;
 
-use ratatui::{buffer::Buffer, layout::Rect};
+use heimdall::log::{RsLevel, RsValue};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color};
 
 pub trait Panel {
     fn render(&self, area: Rect, buf: &mut Buffer);
 }
 
+pub fn level_color(level: RsLevel) -> Color {
+    match level {
+        RsLevel::Trace => Color::DarkGray,
+        RsLevel::Debug => Color::Cyan,
+        RsLevel::Info => Color::Green,
+        RsLevel::Warn => Color::Yellow,
+        RsLevel::Error => Color::Red,
+        RsLevel::Fatal => Color::Magenta,
+    }
+}
+
+fn value_color(value: &RsValue) -> Color {
+    match value {
+        RsValue::Int(_) | RsValue::Float(_) => Color::Cyan,
+        RsValue::Bool(_) => Color::Magenta,
+        RsValue::Str(_) => Color::Yellow,
+        RsValue::Bytes(_) => Color::DarkGray,
+        RsValue::Timestamp(_) => Color::Blue,
+    }
+}
+
 pub mod prelude {
     pub use super::{
         Panel, info::InfoPanel, logs::LogsPanel, status::StatusPanel, threads::ThreadsPanel,
@@ -10,11 +10,19 @@ use super::Panel;
 
 pub struct StatusPanel {
     pub logs_amount: usize,
+    pub evicted: usize,
+    pub skipped: usize,
+    pub rejected: usize,
 }
 
 impl StatusPanel {
-    pub fn from(logs_amount: usize) -> Self {
-        Self { logs_amount }
+    pub fn from(logs_amount: usize, evicted: usize, skipped: usize, rejected: usize) -> Self {
+        Self {
+            logs_amount,
+            evicted,
+            skipped,
+            rejected,
+        }
     }
 }
 
@@ -27,6 +35,10 @@ impl Panel for StatusPanel {
 
         let paragraph = Paragraph::new(vec![
             Line::from(format!("Logs amount: {}", self.logs_amount)),
+            Line::from(format!(
+                "Evicted: {}  Unreadable: {}  Rejected: {}",
+                self.evicted, self.skipped, self.rejected
+            )),
             Line::from("Press q to quit"),
         ]);
         paragraph
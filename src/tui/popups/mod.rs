@@ -1,7 +1,12 @@
 mod exit;
+mod search;
+mod trace;
+mod waterfall;
 
 pub mod prelude {
-    pub use super::{exit::ExitPopup, Popup};
+    pub use super::{
+        exit::ExitPopup, search::SearchPopup, trace::TracePopup, waterfall::WaterfallPopup, Popup,
+    };
 }
 
 use crossterm::event;
@@ -9,6 +14,9 @@ use ratatui::{buffer::Buffer, layout::Rect};
 
 use crate::tui::AppData;
 
+/// Traces with more logs than this are cut off, which the popups show in their title
+const MAX_TRACE_LOGS: usize = 10_000;
+
 pub trait Popup {
     fn priority(&self) -> i32;
     fn area(&self, global_area: Rect) -> Rect;
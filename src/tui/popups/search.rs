@@ -0,0 +1,209 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Clear, List, ListState, Paragraph, StatefulWidget, Widget},
+};
+
+use super::Popup;
+use crate::tui::AppData;
+use heimdall::log::RsLog;
+
+const PAGE_SIZE: usize = 100;
+
+pub struct SearchPopup {
+    query: String,
+    editing: bool,
+    search_requested: bool,
+    page: usize,
+    results: Vec<(usize, RsLog)>,
+    state: ListState,
+    error: Option<String>,
+    exit: bool,
+}
+
+impl SearchPopup {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            editing: true,
+            search_requested: false,
+            page: 0,
+            results: vec![],
+            state: ListState::default(),
+            error: None,
+            exit: false,
+        }
+    }
+
+    fn search(&mut self, data: &AppData) {
+        let data = data.data.lock().unwrap();
+        let result = data
+            .storage
+            .search(&self.query, self.page * PAGE_SIZE, PAGE_SIZE)
+            .and_then(|ids| {
+                let mut logs = Vec::with_capacity(ids.len());
+                for id in ids {
+                    if let Some(log) = data.storage.get_log_by_id(id)? {
+                        logs.push((id, log));
+                    }
+                }
+                Ok(logs)
+            });
+
+        match result {
+            Ok(results) => {
+                self.state
+                    .select(if results.is_empty() { None } else { Some(0) });
+                self.results = results;
+                self.error = None;
+            }
+            Err(e) => {
+                self.results.clear();
+                self.state.select(None);
+                self.error = Some(format!("Search failed: {e}"));
+            }
+        }
+    }
+}
+
+impl Popup for SearchPopup {
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn area(&self, global_area: Rect) -> Rect {
+        let width = global_area.width * 4 / 5;
+        let height = global_area.height * 4 / 5;
+        Rect {
+            x: (global_area.width.saturating_sub(width)) / 2,
+            y: (global_area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        }
+    }
+
+    fn on_event(&mut self, key: KeyEvent) {
+        if self.editing {
+            match key.code {
+                KeyCode::Char(c) => self.query.push(c),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Enter => {
+                    self.editing = false;
+                    self.page = 0;
+                    self.search_requested = true;
+                }
+                KeyCode::Esc => self.exit = true,
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('n') | KeyCode::PageDown if self.results.len() == PAGE_SIZE => {
+                self.page += 1;
+                self.search_requested = true;
+            }
+            KeyCode::Char('p') | KeyCode::PageUp if self.page > 0 => {
+                self.page -= 1;
+                self.search_requested = true;
+            }
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Esc => self.exit = true,
+            _ => {}
+        }
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title("Search")
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded)
+            .style(Style::default().bg(Color::Black));
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let [input, results, help] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(inner);
+
+        let mut input_spans = vec![
+            Span::styled("/ ", Style::default().fg(Color::DarkGray)),
+            Span::raw(self.query.as_str()),
+        ];
+        if self.editing {
+            input_spans.push(Span::styled(
+                "_",
+                Style::default().add_modifier(Modifier::SLOW_BLINK),
+            ));
+        }
+        Paragraph::new(Line::from(input_spans)).render(input, buf);
+
+        if let Some(error) = &self.error {
+            Paragraph::new(Span::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            ))
+            .render(results, buf);
+        } else if self.results.is_empty() && !self.editing {
+            Paragraph::new(Span::styled(
+                "No matches",
+                Style::default().add_modifier(Modifier::ITALIC),
+            ))
+            .render(results, buf);
+        } else {
+            let lines = self
+                .results
+                .iter()
+                .map(|(_, log)| {
+                    Line::from(vec![
+                        Span::styled(
+                            format!("{}", log.ts.format("%H:%M:%S%.6f")),
+                            Style::default()
+                                .fg(Color::Blue)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(": ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(log.msg.as_str()),
+                    ])
+                })
+                .collect::<Vec<_>>();
+            let list = List::new(lines)
+                .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+            let mut state = self.state.clone();
+            StatefulWidget::render(list, results, buf, &mut state);
+        }
+
+        let help_text = if self.editing {
+            "Enter to search, Esc to close".to_string()
+        } else {
+            format!(
+                "Page {}: n/p to page, / to edit, Esc to close",
+                self.page + 1
+            )
+        };
+        Paragraph::new(Span::styled(
+            help_text,
+            Style::default().fg(Color::DarkGray),
+        ))
+        .render(help, buf);
+    }
+
+    fn update(&mut self, data: &mut AppData) -> bool {
+        if self.search_requested {
+            self.search_requested = false;
+            self.search(data);
+        }
+        self.exit
+    }
+}
@@ -0,0 +1,206 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Clear, List, ListState, Paragraph, StatefulWidget, Widget},
+};
+use std::collections::HashMap;
+
+use super::{MAX_TRACE_LOGS, Popup};
+use crate::tui::{AppData, panels::level_color};
+use heimdall::log::{RsKind, RsLog};
+
+pub struct TracePopup {
+    trace_id: String,
+    loaded: bool,
+    logs: Vec<(usize, RsLog)>,
+    /// How many logs the trace has, more than were loaded if it was cut off
+    total: usize,
+    state: ListState,
+    error: Option<String>,
+    exit: bool,
+}
+
+impl TracePopup {
+    pub fn new(trace_id: String) -> Self {
+        Self {
+            trace_id,
+            loaded: false,
+            logs: vec![],
+            total: 0,
+            state: ListState::default(),
+            error: None,
+            exit: false,
+        }
+    }
+
+    fn load(&mut self, data: &AppData) {
+        let trace = data
+            .data
+            .lock()
+            .unwrap()
+            .storage
+            .get_trace(&self.trace_id, 0, MAX_TRACE_LOGS);
+        match trace {
+            Ok(trace) => {
+                self.state
+                    .select(if trace.logs.is_empty() { None } else { Some(0) });
+                self.logs = trace.logs;
+                self.total = trace.total;
+            }
+            Err(e) => self.error = Some(format!("Failed to load the trace: {e}")),
+        }
+    }
+}
+
+/// How deep each log of a trace is nested, spans count their parents and events the span they
+/// were made in. Spans that were not received yet are treated as roots.
+pub fn span_depths(logs: &[(usize, RsLog)]) -> Vec<usize> {
+    let parents: HashMap<&str, &str> = logs
+        .iter()
+        .filter(|(_, log)| matches!(log.kind, RsKind::Span { .. }))
+        .filter_map(|(_, log)| {
+            let trace = log.trace.as_ref()?;
+            Some((trace.span_id.as_str(), trace.parent_span_id.as_deref()?))
+        })
+        .collect();
+    let depth_of = |span_id: &str| {
+        let mut depth = 0;
+        let mut current = span_id;
+        // Bounded, ids from clients may form a cycle
+        while let Some(parent) = parents.get(current) {
+            depth += 1;
+            current = parent;
+            if depth > parents.len() {
+                break;
+            }
+        }
+        depth
+    };
+
+    logs.iter()
+        .map(|(_, log)| match (&log.trace, log.kind) {
+            (Some(trace), RsKind::Span { .. }) => depth_of(&trace.span_id),
+            (Some(trace), RsKind::Event) => depth_of(&trace.span_id) + 1,
+            (None, _) => 0,
+        })
+        .collect()
+}
+
+impl Popup for TracePopup {
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn area(&self, global_area: Rect) -> Rect {
+        let width = global_area.width * 4 / 5;
+        let height = global_area.height * 4 / 5;
+        Rect {
+            x: (global_area.width.saturating_sub(width)) / 2,
+            y: (global_area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        }
+    }
+
+    fn on_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Esc | KeyCode::Char('t') => self.exit = true,
+            _ => {}
+        }
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title(if self.logs.len() < self.total {
+                format!(
+                    "Trace {} (first {} of {} logs)",
+                    self.trace_id,
+                    self.logs.len(),
+                    self.total
+                )
+            } else {
+                format!("Trace {}", self.trace_id)
+            })
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded)
+            .style(Style::default().bg(Color::Black));
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let [logs, help] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(inner);
+
+        if let Some(error) = &self.error {
+            Paragraph::new(Span::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            ))
+            .render(logs, buf);
+        } else if self.logs.is_empty() {
+            Paragraph::new(Span::styled(
+                "No logs in this trace",
+                Style::default().add_modifier(Modifier::ITALIC),
+            ))
+            .render(logs, buf);
+        } else {
+            let start = self.logs[0].1.ts;
+            let lines = self
+                .logs
+                .iter()
+                .zip(span_depths(&self.logs))
+                .map(|((_, log), depth)| {
+                    let offset = (log.ts - start).num_microseconds().unwrap_or(0) as f64 / 1000.0;
+                    let mut spans = vec![
+                        Span::styled(
+                            format!("{offset:>+10.3}ms "),
+                            Style::default().fg(Color::Blue),
+                        ),
+                        Span::styled(
+                            format!("{:>5} ", log.level.as_str().to_uppercase()),
+                            Style::default().fg(level_color(log.level)),
+                        ),
+                        Span::raw("  ".repeat(depth)),
+                    ];
+                    match log.kind.duration() {
+                        Some(duration) => {
+                            spans.push(Span::styled(
+                                log.msg.as_str(),
+                                Style::default().add_modifier(Modifier::BOLD),
+                            ));
+                            spans.push(Span::styled(
+                                format!(" {:.3}ms", duration.as_secs_f64() * 1000.0),
+                                Style::default().fg(Color::DarkGray),
+                            ));
+                        }
+                        None => spans.push(Span::raw(log.msg.as_str())),
+                    }
+                    Line::from(spans)
+                })
+                .collect::<Vec<_>>();
+            let list = List::new(lines)
+                .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+            let mut state = self.state.clone();
+            StatefulWidget::render(list, logs, buf, &mut state);
+        }
+
+        Paragraph::new(Span::styled(
+            format!("{} logs, Esc to close", self.logs.len()),
+            Style::default().fg(Color::DarkGray),
+        ))
+        .render(help, buf);
+    }
+
+    fn update(&mut self, data: &mut AppData) -> bool {
+        if !self.loaded {
+            self.loaded = true;
+            self.load(data);
+        }
+        self.exit
+    }
+}
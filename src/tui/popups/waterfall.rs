@@ -0,0 +1,314 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Clear, List, ListState, Paragraph, StatefulWidget, Widget},
+};
+use std::collections::{HashMap, HashSet};
+
+use super::{MAX_TRACE_LOGS, Popup};
+use crate::tui::{AppData, panels::level_color};
+use heimdall::log::{RsKind, RsLevel, RsLog};
+
+/// Bar colors by nesting depth
+const SPAN_COLORS: [Color; 4] = [Color::Cyan, Color::Blue, Color::Magenta, Color::Yellow];
+
+/// One line of the waterfall, times are in microseconds since the trace started
+struct Row {
+    depth: usize,
+    label: String,
+    level: RsLevel,
+    start: i64,
+    /// `None` for logs, which are drawn as a marker
+    duration: Option<i64>,
+}
+
+pub struct WaterfallPopup {
+    trace_id: String,
+    loaded: bool,
+    rows: Vec<Row>,
+    /// Time from the first span opening to the last one closing
+    total: i64,
+    /// How many logs the trace has if it was cut off
+    truncated: Option<usize>,
+    state: ListState,
+    error: Option<String>,
+    exit: bool,
+}
+
+impl WaterfallPopup {
+    pub fn new(trace_id: String) -> Self {
+        Self {
+            trace_id,
+            loaded: false,
+            rows: vec![],
+            total: 0,
+            truncated: None,
+            state: ListState::default(),
+            error: None,
+            exit: false,
+        }
+    }
+
+    fn load(&mut self, data: &AppData) {
+        let trace = data
+            .data
+            .lock()
+            .unwrap()
+            .storage
+            .get_trace(&self.trace_id, 0, MAX_TRACE_LOGS);
+        let logs = match trace {
+            Ok(trace) => {
+                self.truncated = (trace.logs.len() < trace.total).then_some(trace.total);
+                trace.logs
+            }
+            Err(e) => {
+                self.error = Some(format!("Failed to load the trace: {e}"));
+                return;
+            }
+        };
+        let Some((_, first)) = logs.first() else {
+            return;
+        };
+
+        let start = first.ts;
+        let offset = |log: &RsLog| (log.ts - start).num_microseconds().unwrap_or(0);
+        self.rows = rows(&logs, offset);
+        self.total = logs
+            .iter()
+            .map(|(_, log)| {
+                offset(log)
+                    + log
+                        .kind
+                        .duration()
+                        .map_or(0, |duration| duration.as_micros() as i64)
+            })
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        self.state
+            .select(if self.rows.is_empty() { None } else { Some(0) });
+    }
+}
+
+/// Orders a trace depth first: each span is followed by the logs made in it, then by the spans
+/// opened in it. Logs of spans that were not received are left at the end.
+fn rows(logs: &[(usize, RsLog)], offset: impl Fn(&RsLog) -> i64) -> Vec<Row> {
+    let mut roots = Vec::new();
+    let mut children: HashMap<&str, Vec<&RsLog>> = HashMap::new();
+    let mut events: HashMap<&str, Vec<&RsLog>> = HashMap::new();
+    let spans: HashSet<&str> = logs
+        .iter()
+        .filter(|(_, log)| matches!(log.kind, RsKind::Span { .. }))
+        .filter_map(|(_, log)| Some(log.trace.as_ref()?.span_id.as_str()))
+        .collect();
+
+    // Logs are sorted by timestamp already, so siblings stay in the order they started
+    for (_, log) in logs {
+        let Some(trace) = &log.trace else {
+            continue;
+        };
+        match log.kind {
+            RsKind::Span { .. } => match trace.parent_span_id.as_deref() {
+                Some(parent) if spans.contains(parent) => {
+                    children.entry(parent).or_default().push(log)
+                }
+                _ => roots.push(log),
+            },
+            RsKind::Event => events.entry(&trace.span_id).or_default().push(log),
+        }
+    }
+
+    let mut rows = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack: Vec<(&RsLog, usize)> = roots.into_iter().rev().map(|log| (log, 0)).collect();
+    while let Some((span, depth)) = stack.pop() {
+        let span_id = span
+            .trace
+            .as_ref()
+            .map_or("", |trace| trace.span_id.as_str());
+        // Ids come from clients, a span that is its own ancestor is shown once
+        if !visited.insert(span_id) {
+            continue;
+        }
+        rows.push(Row {
+            depth,
+            label: span.msg.clone(),
+            level: span.level,
+            start: offset(span),
+            duration: span
+                .kind
+                .duration()
+                .map(|duration| duration.as_micros() as i64),
+        });
+        for event in events.remove(span_id).unwrap_or_default() {
+            rows.push(event_row(event, depth + 1, &offset));
+        }
+        for child in children
+            .remove(span_id)
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+        {
+            stack.push((child, depth + 1));
+        }
+    }
+
+    let mut orphans = events.into_values().flatten().collect::<Vec<_>>();
+    orphans.sort_by_key(|log| log.ts);
+    rows.extend(orphans.into_iter().map(|log| event_row(log, 0, &offset)));
+    rows
+}
+
+fn event_row(log: &RsLog, depth: usize, offset: &impl Fn(&RsLog) -> i64) -> Row {
+    Row {
+        depth,
+        label: log.msg.clone(),
+        level: log.level,
+        start: offset(log),
+        duration: None,
+    }
+}
+
+fn format_micros(micros: i64) -> String {
+    format!("{:.3}ms", micros as f64 / 1000.0)
+}
+
+impl Popup for WaterfallPopup {
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn area(&self, global_area: Rect) -> Rect {
+        let width = global_area.width * 9 / 10;
+        let height = global_area.height * 4 / 5;
+        Rect {
+            x: (global_area.width.saturating_sub(width)) / 2,
+            y: (global_area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        }
+    }
+
+    fn on_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Esc | KeyCode::Char('T') => self.exit = true,
+            _ => {}
+        }
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title(match self.truncated {
+                Some(total) => format!(
+                    "Waterfall {} (first {MAX_TRACE_LOGS} of {total} logs)",
+                    self.trace_id
+                ),
+                None => format!("Waterfall {}", self.trace_id),
+            })
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded)
+            .style(Style::default().bg(Color::Black));
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let [header, rows_area, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(inner);
+
+        if let Some(error) = &self.error {
+            Paragraph::new(Span::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            ))
+            .render(rows_area, buf);
+        } else if self.rows.is_empty() {
+            Paragraph::new(Span::styled(
+                "No spans in this trace",
+                Style::default().add_modifier(Modifier::ITALIC),
+            ))
+            .render(rows_area, buf);
+        } else {
+            let label_width = (inner.width as usize / 3).clamp(10, 50);
+            let timeline_width = (inner.width as usize)
+                .saturating_sub(label_width + 1)
+                .max(1);
+            let column = |micros: i64| {
+                ((micros.max(0) as f64 / self.total as f64) * timeline_width as f64) as usize
+            };
+
+            let total = format_micros(self.total);
+            Paragraph::new(Line::from(vec![
+                Span::raw(" ".repeat(label_width + 1)),
+                Span::styled(
+                    format!(
+                        "0ms{total:>width$}",
+                        width = timeline_width.saturating_sub(3)
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+            .render(header, buf);
+
+            let lines = self
+                .rows
+                .iter()
+                .map(|row| {
+                    let mut label = format!("{}{}", "  ".repeat(row.depth), row.label);
+                    if let Some(duration) = row.duration {
+                        label = format!("{label} {}", format_micros(duration));
+                    }
+                    let label: String = label.chars().take(label_width).collect();
+
+                    let start = column(row.start).min(timeline_width - 1);
+                    let (bar, style) = match row.duration {
+                        Some(duration) => (
+                            "█".repeat(
+                                column(row.start + duration)
+                                    .saturating_sub(start)
+                                    .clamp(1, timeline_width - start),
+                            ),
+                            Style::default().fg(SPAN_COLORS[row.depth % SPAN_COLORS.len()]),
+                        ),
+                        None => ("•".to_string(), Style::default().fg(level_color(row.level))),
+                    };
+                    let label_style = match row.duration {
+                        Some(_) => Style::default().add_modifier(Modifier::BOLD),
+                        None => Style::default().fg(level_color(row.level)),
+                    };
+
+                    Line::from(vec![
+                        Span::styled(format!("{label:<label_width$} "), label_style),
+                        Span::raw(" ".repeat(start)),
+                        Span::styled(bar, style),
+                    ])
+                })
+                .collect::<Vec<_>>();
+            let list = List::new(lines).highlight_style(Style::default().bg(Color::DarkGray));
+            let mut state = self.state.clone();
+            StatefulWidget::render(list, rows_area, buf, &mut state);
+        }
+
+        Paragraph::new(Span::styled(
+            "j/k to scroll, Esc to close",
+            Style::default().fg(Color::DarkGray),
+        ))
+        .render(help, buf);
+    }
+
+    fn update(&mut self, data: &mut AppData) -> bool {
+        if !self.loaded {
+            self.loaded = true;
+            self.load(data);
+        }
+        self.exit
+    }
+}